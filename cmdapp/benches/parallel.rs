@@ -0,0 +1,67 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use vtracer::{convert, ColorImage, Config, Preset};
+
+const SIZE: usize = 2000;
+
+/// A photo-like input: smooth gradients with overlapping blobs and a little noise
+fn synthetic_photo() -> ColorImage {
+    let rng = fastrand::Rng::with_seed(7);
+    let blobs: Vec<_> = (0..40)
+        .map(|_| {
+            let center = (rng.f64() * SIZE as f64, rng.f64() * SIZE as f64);
+            let radius = 50.0 + rng.f64() * 250.0;
+            let color = [rng.u8(..), rng.u8(..), rng.u8(..)];
+            (center, radius, color)
+        })
+        .collect();
+
+    let mut pixels = Vec::with_capacity(SIZE * SIZE * 4);
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let mut rgb = [
+                (x * 255 / SIZE) as f64,
+                (y * 255 / SIZE) as f64,
+                ((x + y) * 255 / (2 * SIZE)) as f64,
+            ];
+            for ((cx, cy), radius, color) in &blobs {
+                let distance = ((x as f64 - cx).powi(2) + (y as f64 - cy).powi(2)).sqrt();
+                let weight = (1.0 - distance / radius).max(0.0);
+                for c in 0..3 {
+                    rgb[c] += (color[c] as f64 - rgb[c]) * weight;
+                }
+            }
+            for value in rgb {
+                pixels.push((value + rng.f64() * 8.0).min(255.0) as u8);
+            }
+            pixels.push(255);
+        }
+    }
+
+    ColorImage {
+        pixels,
+        width: SIZE,
+        height: SIZE,
+    }
+}
+
+fn photo_preset(c: &mut Criterion) {
+    let img = synthetic_photo();
+    let mut group = c.benchmark_group("photo_2000x2000");
+    group.sample_size(10);
+    for parallel in [false, true] {
+        let name = if parallel { "parallel" } else { "serial" };
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let config = Config {
+                    parallel,
+                    ..Config::from_preset(Preset::Photo)
+                };
+                convert(img.clone(), config).unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, photo_preset);
+criterion_main!(benches);
@@ -0,0 +1,101 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::config::Config;
+use super::converter::convert_image_to_svg;
+
+/// File extensions (lowercase) picked up by `batch_convert`.
+/// `jxl`, `avif` and `psd` files are also picked up when the corresponding feature is enabled.
+pub const BATCH_EXTENSIONS: [&str; 6] = ["png", "jpg", "jpeg", "bmp", "gif", "webp"];
+
+/// Outcome of a `batch_convert` run
+#[derive(Default)]
+pub struct BatchResult {
+    /// Paths of the svg files written
+    pub converted: Vec<PathBuf>,
+    /// Input paths that could not be converted, with the error message
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+impl BatchResult {
+    pub fn is_ok(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// Convert every supported image in `input_dir` into an svg file of the same name in `output_dir`,
+/// or a file of the format given by `config.output_format`.
+/// Subdirectories are mirrored into `output_dir` when `recursive` is set.
+/// A failure on one file does not stop the batch; it is collected into the result instead.
+pub fn batch_convert(
+    input_dir: &Path,
+    output_dir: &Path,
+    config: Config,
+    recursive: bool,
+) -> BatchResult {
+    let mut result = BatchResult::default();
+    batch_convert_dir(input_dir, output_dir, &config, recursive, &mut result);
+    result
+}
+
+fn batch_convert_dir(
+    input_dir: &Path,
+    output_dir: &Path,
+    config: &Config,
+    recursive: bool,
+    result: &mut BatchResult,
+) {
+    let mut entries = match fs::read_dir(input_dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect::<Vec<_>>(),
+        Err(_) => {
+            let msg = String::from("Cannot read input directory.");
+            result.failed.push((input_dir.to_path_buf(), msg));
+            return;
+        }
+    };
+    entries.sort();
+
+    if fs::create_dir_all(output_dir).is_err() {
+        let msg = String::from("Cannot create output directory.");
+        result.failed.push((input_dir.to_path_buf(), msg));
+        return;
+    }
+
+    for input_path in entries {
+        let file_name = match input_path.file_name() {
+            Some(file_name) => file_name,
+            None => continue,
+        };
+        if input_path.is_dir() {
+            if recursive {
+                let output_dir = output_dir.join(file_name);
+                batch_convert_dir(&input_path, &output_dir, config, recursive, result);
+            }
+        } else if is_supported_image(&input_path) {
+            let extension = config.output_format.extension();
+            let output_path = output_dir.join(file_name).with_extension(extension);
+            log::debug!("Converting {}", input_path.display());
+            match convert_image_to_svg(&input_path, &output_path, config.clone()) {
+                Ok(()) => result.converted.push(output_path),
+                Err(msg) => result.failed.push((input_path, msg)),
+            }
+        } else {
+            log::trace!("Skipping {}", input_path.display());
+        }
+    }
+}
+
+fn is_supported_image(path: &Path) -> bool {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => {
+            let ext = ext.to_lowercase();
+            BATCH_EXTENSIONS.contains(&ext.as_str())
+                || (cfg!(feature = "jxl") && ext == "jxl")
+                || (cfg!(feature = "avif") && ext == "avif")
+                || (cfg!(feature = "psd") && ext == "psd")
+        }
+        None => false,
+    }
+}
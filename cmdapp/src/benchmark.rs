@@ -0,0 +1,50 @@
+use std::time::Instant;
+
+use image::DynamicImage;
+
+use super::config::Config;
+use super::converter::{convert, dynamic_image_to_color_image};
+use super::error::VTracerError;
+
+/// Timings of repeated conversions of one image, see `benchmark`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BenchmarkResult {
+    pub mean_ms: f64,
+    /// Population standard deviation of the measured runs
+    pub std_dev_ms: f64,
+    pub min_ms: f64,
+    pub max_ms: f64,
+    /// Size of the SVG written by the last run, in `config.output_encoding`
+    pub output_size_bytes: usize,
+}
+
+/// Convert `image` to an SVG `runs` times, at least twice, and time every run but the first,
+/// which warms up caches and allocations. A run covers the whole conversion from the
+/// `DynamicImage` to the SVG bytes. `config.output_format` is ignored.
+pub fn benchmark(
+    image: &DynamicImage,
+    config: &Config,
+    runs: u32,
+) -> Result<BenchmarkResult, VTracerError> {
+    let mut timings = vec![];
+    let mut output_size_bytes = 0;
+    for _ in 0..runs.max(2) {
+        let start = Instant::now();
+        let svg = convert(dynamic_image_to_color_image(image), config.clone())
+            .map_err(VTracerError::Conversion)?;
+        output_size_bytes = svg.to_bytes().len();
+        timings.push(start.elapsed().as_secs_f64() * 1000.0);
+    }
+    let timings = &timings[1..];
+
+    let mean_ms = timings.iter().sum::<f64>() / timings.len() as f64;
+    let variance =
+        timings.iter().map(|ms| (ms - mean_ms).powi(2)).sum::<f64>() / timings.len() as f64;
+    Ok(BenchmarkResult {
+        mean_ms,
+        std_dev_ms: variance.sqrt(),
+        min_ms: timings.iter().copied().fold(f64::INFINITY, f64::min),
+        max_ms: timings.iter().copied().fold(0.0, f64::max),
+        output_size_bytes,
+    })
+}
@@ -0,0 +1,276 @@
+use visioncortex::{CompoundPath, CompoundPathElement, PointF64, Spline};
+
+/// Distance between the samples taken along polygon edges, in pixels
+const SAMPLE_SPACING: f64 = 1.0;
+const MAX_REPARAMETERIZE_ITERATIONS: usize = 4;
+
+type Curve = [PointF64; 4];
+
+/// Fit the polygons of `path` with cubic Bézier curves using Schneider's algorithm
+/// ("An Algorithm for Automatically Fitting Digitized Curves", Graphics Gems, 1990).
+/// Vertices turning by more than `corner_threshold` radians are kept as corners,
+/// and no curve deviates from the polygon by more than `tolerance` pixels.
+pub(crate) fn fit_compound_path(
+    path: &CompoundPath,
+    tolerance: f64,
+    corner_threshold: f64,
+) -> CompoundPath {
+    let mut fitted = CompoundPath::new();
+    for element in &path.paths {
+        let mut polygon: Vec<PointF64> = match element {
+            CompoundPathElement::PathI32(p) => p.path.iter().map(|p| p.to_point_f64()).collect(),
+            CompoundPathElement::PathF64(p) => p.path.clone(),
+            CompoundPathElement::Spline(_) => {
+                fitted.paths.push(element.clone());
+                continue;
+            }
+        };
+        // Closed paths repeat the first point at the end
+        if polygon.len() > 1 && polygon.first() == polygon.last() {
+            polygon.pop();
+        }
+        if polygon.len() < 3 {
+            continue;
+        }
+        fitted.add_spline(fit_polygon(&polygon, tolerance, corner_threshold));
+    }
+    fitted
+}
+
+fn fit_polygon(polygon: &[PointF64], tolerance: f64, corner_threshold: f64) -> Spline {
+    let n = polygon.len();
+    let is_corner = |i: usize| {
+        let incoming = polygon[i] - polygon[(i + n - 1) % n];
+        let outgoing = polygon[(i + 1) % n] - polygon[i];
+        angle_between(incoming, outgoing) > corner_threshold
+    };
+    let corners: Vec<usize> = (0..n).filter(|&i| is_corner(i)).collect();
+
+    let mut curves = vec![];
+    if corners.is_empty() {
+        // A smooth loop, fitted from vertex 0 with the same tangent at both ends
+        let samples = sample_section(polygon, 0, n);
+        let tangent = (polygon[1] - polygon[n - 1]).get_normalized();
+        fit_cubic(
+            &samples,
+            tangent,
+            -tangent,
+            tolerance * tolerance,
+            &mut curves,
+        );
+    } else {
+        for (k, &start) in corners.iter().enumerate() {
+            let end = corners[(k + 1) % corners.len()];
+            let length = (end + n - start - 1) % n + 1;
+            let samples = sample_section(polygon, start, length);
+            let last = samples.len() - 1;
+            let left = (samples[1] - samples[0]).get_normalized();
+            let right = (samples[last - 1] - samples[last]).get_normalized();
+            fit_cubic(&samples, left, right, tolerance * tolerance, &mut curves);
+        }
+    }
+
+    let mut spline = Spline::new(curves[0][0]);
+    for curve in curves {
+        spline.add(curve[1], curve[2], curve[3]);
+    }
+    spline
+}
+
+/// Points along the `length` polygon edges starting at vertex `start`, including both ends
+fn sample_section(polygon: &[PointF64], start: usize, length: usize) -> Vec<PointF64> {
+    let n = polygon.len();
+    let mut samples = vec![polygon[start]];
+    for i in 0..length {
+        let (from, to) = (polygon[(start + i) % n], polygon[(start + i + 1) % n]);
+        let steps = (from.distance_to(to) / SAMPLE_SPACING).ceil().max(1.0) as usize;
+        for step in 1..=steps {
+            samples.push(from + (to - from) * (step as f64 / steps as f64));
+        }
+    }
+    samples
+}
+
+fn angle_between(a: PointF64, b: PointF64) -> f64 {
+    let (a, b) = (a.get_normalized(), b.get_normalized());
+    a.dot(b).clamp(-1.0, 1.0).acos()
+}
+
+/// Fit `points` with as few curves as possible, splitting at the point of maximum error
+/// until every point lies within `max_error` (squared distance) of the curves
+fn fit_cubic(
+    points: &[PointF64],
+    left_tangent: PointF64,
+    right_tangent: PointF64,
+    max_error: f64,
+    curves: &mut Vec<Curve>,
+) {
+    let (first, last) = (points[0], points[points.len() - 1]);
+    if points.len() == 2 {
+        let distance = first.distance_to(last) / 3.0;
+        curves.push([
+            first,
+            first + left_tangent * distance,
+            last + right_tangent * distance,
+            last,
+        ]);
+        return;
+    }
+
+    let mut params = chord_length_parameterize(points);
+    let mut curve = generate_bezier(points, &params, left_tangent, right_tangent);
+    let (mut error, mut split) = compute_max_error(points, &curve, &params);
+    if error < max_error {
+        curves.push(curve);
+        return;
+    }
+
+    // Close enough that refining the parameters may be all it takes
+    if error < max_error * 4.0 {
+        for _ in 0..MAX_REPARAMETERIZE_ITERATIONS {
+            params = reparameterize(points, &params, &curve);
+            curve = generate_bezier(points, &params, left_tangent, right_tangent);
+            (error, split) = compute_max_error(points, &curve, &params);
+            if error < max_error {
+                curves.push(curve);
+                return;
+            }
+        }
+    }
+
+    let center_tangent = (points[split - 1] - points[split + 1]).get_normalized();
+    fit_cubic(
+        &points[..=split],
+        left_tangent,
+        center_tangent,
+        max_error,
+        curves,
+    );
+    fit_cubic(
+        &points[split..],
+        -center_tangent,
+        right_tangent,
+        max_error,
+        curves,
+    );
+}
+
+/// Least squares fit of the inner control points along the given end tangents
+fn generate_bezier(
+    points: &[PointF64],
+    params: &[f64],
+    left_tangent: PointF64,
+    right_tangent: PointF64,
+) -> Curve {
+    let (first, last) = (points[0], points[points.len() - 1]);
+    let mut c = [[0.0; 2]; 2];
+    let mut x = [0.0; 2];
+    for (&point, &u) in points.iter().zip(params) {
+        let a0 = left_tangent * (3.0 * u * (1.0 - u) * (1.0 - u));
+        let a1 = right_tangent * (3.0 * u * u * (1.0 - u));
+        c[0][0] += a0.dot(a0);
+        c[0][1] += a0.dot(a1);
+        c[1][1] += a1.dot(a1);
+        let tmp = point - bezier_point(&[first, first, last, last], u);
+        x[0] += a0.dot(tmp);
+        x[1] += a1.dot(tmp);
+    }
+    c[1][0] = c[0][1];
+
+    let det_c0_c1 = c[0][0] * c[1][1] - c[1][0] * c[0][1];
+    let det_c0_x = c[0][0] * x[1] - c[1][0] * x[0];
+    let det_x_c1 = x[0] * c[1][1] - x[1] * c[0][1];
+    let (alpha_l, alpha_r) = if det_c0_c1 == 0.0 {
+        (0.0, 0.0)
+    } else {
+        (det_x_c1 / det_c0_c1, det_c0_x / det_c0_c1)
+    };
+
+    // Degenerate solutions fall back to the Wu/Barsky heuristic
+    let segment_length = first.distance_to(last);
+    let epsilon = 1e-6 * segment_length;
+    if alpha_l < epsilon || alpha_r < epsilon {
+        let distance = segment_length / 3.0;
+        return [
+            first,
+            first + left_tangent * distance,
+            last + right_tangent * distance,
+            last,
+        ];
+    }
+    [
+        first,
+        first + left_tangent * alpha_l,
+        last + right_tangent * alpha_r,
+        last,
+    ]
+}
+
+fn chord_length_parameterize(points: &[PointF64]) -> Vec<f64> {
+    let mut params = vec![0.0];
+    for pair in points.windows(2) {
+        params.push(params[params.len() - 1] + pair[0].distance_to(pair[1]));
+    }
+    let total = params[params.len() - 1];
+    if total > 0.0 {
+        for u in params.iter_mut() {
+            *u /= total;
+        }
+    }
+    params
+}
+
+/// Improve the parameter of every point with a Newton-Raphson step
+fn reparameterize(points: &[PointF64], params: &[f64], curve: &Curve) -> Vec<f64> {
+    points
+        .iter()
+        .zip(params)
+        .map(|(&point, &u)| newton_raphson_root_find(curve, point, u))
+        .collect()
+}
+
+fn newton_raphson_root_find(curve: &Curve, point: PointF64, u: f64) -> f64 {
+    let d1 = [
+        (curve[1] - curve[0]) * 3.0,
+        (curve[2] - curve[1]) * 3.0,
+        (curve[3] - curve[2]) * 3.0,
+    ];
+    let d2 = [(d1[1] - d1[0]) * 2.0, (d1[2] - d1[1]) * 2.0];
+
+    let q = bezier_point(curve, u) - point;
+    let q1 = quadratic_point(&d1, u);
+    let q2 = d2[0] * (1.0 - u) + d2[1] * u;
+    let denominator = q1.dot(q1) + q.dot(q2);
+    if denominator == 0.0 {
+        return u;
+    }
+    (u - q.dot(q1) / denominator).clamp(0.0, 1.0)
+}
+
+/// Maximum squared distance of the points to the curve, and the index of that point
+fn compute_max_error(points: &[PointF64], curve: &Curve, params: &[f64]) -> (f64, usize) {
+    let mut max_error = 0.0;
+    let mut split = points.len() / 2;
+    for i in 1..points.len() - 1 {
+        let diff = bezier_point(curve, params[i]) - points[i];
+        let error = diff.dot(diff);
+        if error >= max_error {
+            max_error = error;
+            split = i;
+        }
+    }
+    (max_error, split)
+}
+
+fn bezier_point(curve: &Curve, t: f64) -> PointF64 {
+    let u = 1.0 - t;
+    curve[0] * (u * u * u)
+        + curve[1] * (3.0 * u * u * t)
+        + curve[2] * (3.0 * u * t * t)
+        + curve[3] * (t * t * t)
+}
+
+fn quadratic_point(curve: &[PointF64; 3], t: f64) -> PointF64 {
+    let u = 1.0 - t;
+    curve[0] * (u * u) + curve[1] * (2.0 * u * t) + curve[2] * (t * t)
+}
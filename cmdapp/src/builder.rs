@@ -0,0 +1,318 @@
+use super::config::{
+    is_valid_id_prefix, AspectRatio, AspectRatioFit, BlendMode, ColorMode, ColorSpace, Config,
+    ConverterConfig, Hierarchical, InputColorspace, OutputEncoding, OutputFormat, PathSimplifyMode,
+    PathSort, Rect, Rgba, StrokeMode, Unit,
+};
+use super::error::VTracerError;
+use std::path::PathBuf;
+
+/// Builds a `Config` field by field, starting from `Config::default()`.
+/// Setters of optional fields take the value itself and enable the option.
+#[derive(Clone, Default)]
+pub struct ConfigBuilder {
+    config: Config,
+}
+
+macro_rules! setters {
+    ($($field:ident: $ty:ty),* $(,)?) => {
+        $(
+            pub fn $field(mut self, $field: $ty) -> Self {
+                self.config.$field = $field;
+                self
+            }
+        )*
+    };
+}
+
+macro_rules! option_setters {
+    ($($field:ident: $ty:ty),* $(,)?) => {
+        $(
+            pub fn $field(mut self, $field: $ty) -> Self {
+                self.config.$field = Some($field);
+                self
+            }
+        )*
+    };
+}
+
+impl ConfigBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start from a preset instead of the default config
+    pub fn from_config(config: Config) -> Self {
+        Self { config }
+    }
+
+    setters! {
+        color_mode: ColorMode,
+        hierarchical: Hierarchical,
+        filter_speckle: usize,
+        color_precision: i32,
+        layer_difference: i32,
+        color_space: ColorSpace,
+        input_colorspace: InputColorspace,
+        mode: PathSimplifyMode,
+        corner_threshold: i32,
+        length_threshold: f64,
+        max_iterations: usize,
+        splice_threshold: i32,
+        path_precision: Option<u32>,
+        bezier_tolerance: f64,
+        corner_rounding: f64,
+        path_join_tolerance: f64,
+        anti_alias: bool,
+        invert: bool,
+        ignore_alpha: bool,
+        alpha_threshold: u8,
+        grayscale_gamma: f64,
+        grayscale_weights: (f64, f64, f64),
+        binary_threshold: u8,
+        binary_threshold_otsu: bool,
+        dither: bool,
+        canny_preprocess: bool,
+        canny_low_threshold: f64,
+        canny_high_threshold: f64,
+        stroke_mode: StrokeMode,
+        global_opacity: f64,
+        aspect_ratio_fit: AspectRatioFit,
+        viewbox_margin: u32,
+        clip_to_bounds: bool,
+        scale: f64,
+        preserve_dpi: bool,
+        layer_names: bool,
+        output_id_prefix: String,
+        embed_original: bool,
+        optimize_svg: bool,
+        dedupe_paths: bool,
+        path_sort: PathSort,
+        close_paths: bool,
+        output_encoding: OutputEncoding,
+        output_format: OutputFormat,
+        split_by_color: bool,
+        parallel: bool,
+    }
+
+    option_setters! {
+        filter_speckle_area_direct: f64,
+        max_colors: usize,
+        layer_limit: usize,
+        max_paths_per_cluster: usize,
+        palette_file: PathBuf,
+        min_path_length: f64,
+        stroke_width: f64,
+        background_color: Rgba,
+        blend_mode: BlendMode,
+        resize_to: (u32, u32),
+        region: Rect,
+        translate: (f64, f64),
+        aspect_ratio: AspectRatio,
+        unit: Unit,
+        svg_title: String,
+        svg_desc: String,
+        output_indent: u32,
+        max_output_bytes: usize,
+        cpu_threads: usize,
+        random_seed: u64,
+    }
+
+    /// Check that every numeric field lies within the range accepted by the command line
+    pub fn build(self) -> Result<Config, VTracerError> {
+        let config = self.config;
+        check_range("filter_speckle", config.filter_speckle as f64, 0.0, 16.0)?;
+        if let Some(area) = config.filter_speckle_area_direct {
+            check_range("filter_speckle_area_direct", area, 0.0, f64::INFINITY)?;
+        }
+        check_range("color_precision", config.color_precision as f64, 1.0, 8.0)?;
+        check_range(
+            "layer_difference",
+            config.layer_difference as f64,
+            0.0,
+            255.0,
+        )?;
+        check_range(
+            "corner_threshold",
+            config.corner_threshold as f64,
+            0.0,
+            180.0,
+        )?;
+        check_range("length_threshold", config.length_threshold, 3.5, 10.0)?;
+        check_range("max_iterations", config.max_iterations as f64, 1.0, 100.0)?;
+        check_range(
+            "splice_threshold",
+            config.splice_threshold as f64,
+            0.0,
+            180.0,
+        )?;
+        check_range("bezier_tolerance", config.bezier_tolerance, 0.01, 10.0)?;
+        check_range("corner_rounding", config.corner_rounding, 0.0, 100.0)?;
+        check_range("path_join_tolerance", config.path_join_tolerance, 0.0, 10.0)?;
+        check_range("grayscale_gamma", config.grayscale_gamma, 0.1, 3.0)?;
+        let (r, g, b) = config.grayscale_weights;
+        check_range("grayscale_weights", r + g + b, 0.99, 1.01)?;
+        check_range("global_opacity", config.global_opacity, 0.0, 1.0)?;
+        check_range(
+            "canny_high_threshold",
+            config.canny_high_threshold,
+            0.0,
+            255.0,
+        )?;
+        check_range(
+            "canny_low_threshold",
+            config.canny_low_threshold,
+            0.0,
+            config.canny_high_threshold,
+        )?;
+        if let Some(max_colors) = config.max_colors {
+            check_range("max_colors", max_colors as f64, 2.0, 256.0)?;
+        }
+        if let Some(layer_limit) = config.layer_limit {
+            check_range("layer_limit", layer_limit as f64, 1.0, f64::INFINITY)?;
+        }
+        if let Some(max_paths) = config.max_paths_per_cluster {
+            check_range(
+                "max_paths_per_cluster",
+                max_paths as f64,
+                1.0,
+                f64::INFINITY,
+            )?;
+        }
+        if let Some(indent) = config.output_indent {
+            check_range("output_indent", indent as f64, 0.0, 16.0)?;
+        }
+        if let Some(max_bytes) = config.max_output_bytes {
+            check_range("max_output_bytes", max_bytes as f64, 1.0, f64::INFINITY)?;
+        }
+        if let Some((width, height)) = config.resize_to {
+            check_range("resize_to", width.min(height) as f64, 1.0, f64::INFINITY)?;
+        }
+        if let Some(cpu_threads) = config.cpu_threads {
+            check_range("cpu_threads", cpu_threads as f64, 1.0, f64::INFINITY)?;
+        }
+        if let Some(length) = config.min_path_length {
+            check_range("min_path_length", length, 0.0, f64::INFINITY)?;
+        }
+        if let Some(stroke_width) = config.stroke_width {
+            check_range("stroke_width", stroke_width, 0.0, 100.0)?;
+        }
+        // Zero is out of range too
+        if !config.scale.is_finite() || config.scale <= 0.0 {
+            return Err(VTracerError::OutOfRange {
+                param: "scale",
+                value: config.scale,
+                min: 0.0,
+                max: f64::INFINITY,
+            });
+        }
+        if !is_valid_id_prefix(&config.output_id_prefix) {
+            return Err(VTracerError::ParseError {
+                param: "output_id_prefix",
+                raw: config.output_id_prefix,
+            });
+        }
+        Ok(config)
+    }
+}
+
+fn check_range(param: &'static str, value: f64, min: f64, max: f64) -> Result<(), VTracerError> {
+    // Written so that NaN is out of range too
+    if value >= min && value <= max {
+        Ok(())
+    } else {
+        Err(VTracerError::OutOfRange {
+            param,
+            value,
+            min,
+            max,
+        })
+    }
+}
+
+/// Builds a `ConverterConfig` field by field, starting from `ConverterConfig::default()`.
+/// Values are taken as they are, without the range checks of `ConfigBuilder::build`.
+#[derive(Clone, Default)]
+pub struct ConverterConfigBuilder {
+    config: ConverterConfig,
+}
+
+impl ConverterConfigBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start from an existing converter config
+    pub fn from_converter_config(config: ConverterConfig) -> Self {
+        Self { config }
+    }
+
+    setters! {
+        color_mode: ColorMode,
+        hierarchical: Hierarchical,
+        filter_speckle_area: usize,
+        color_precision_loss: i32,
+        layer_difference: i32,
+        color_space: ColorSpace,
+        input_colorspace: InputColorspace,
+        mode: PathSimplifyMode,
+        corner_threshold: f64,
+        length_threshold: f64,
+        max_iterations: usize,
+        splice_threshold: f64,
+        path_precision: Option<u32>,
+        bezier_tolerance: f64,
+        corner_rounding: f64,
+        path_join_tolerance: f64,
+        binary_threshold: Option<u8>,
+        dither: bool,
+        stroke_width: Option<f64>,
+        global_opacity: f64,
+        anti_alias: bool,
+        invert: bool,
+        ignore_alpha: bool,
+        alpha_threshold: u8,
+        grayscale_gamma: f64,
+        grayscale_weights: (f64, f64, f64),
+        canny_preprocess: bool,
+        canny_low_threshold: f64,
+        canny_high_threshold: f64,
+        aspect_ratio_fit: AspectRatioFit,
+        viewbox_margin: u32,
+        clip_to_bounds: bool,
+        scale: f64,
+        layer_names: bool,
+        output_id_prefix: String,
+        embed_original: bool,
+        optimize_svg: bool,
+        dedupe_paths: bool,
+        path_sort: PathSort,
+        close_paths: bool,
+        output_encoding: OutputEncoding,
+        parallel: bool,
+    }
+
+    option_setters! {
+        max_colors: usize,
+        layer_limit: usize,
+        max_paths_per_cluster: usize,
+        palette_file: PathBuf,
+        min_path_length: f64,
+        background_color: Rgba,
+        blend_mode: BlendMode,
+        resize_to: (u32, u32),
+        region: Rect,
+        translate: (f64, f64),
+        aspect_ratio: AspectRatio,
+        unit: Unit,
+        svg_title: String,
+        svg_desc: String,
+        output_indent: u32,
+        max_output_bytes: usize,
+        cpu_threads: usize,
+        random_seed: u64,
+    }
+
+    pub fn build(self) -> ConverterConfig {
+        self.config
+    }
+}
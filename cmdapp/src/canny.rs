@@ -0,0 +1,149 @@
+use visioncortex::{BinaryImage, ColorImage};
+
+use super::converter::luminance;
+
+/// Standard deviation of the Gaussian blur, in pixels
+const BLUR_SIGMA: f64 = 1.4;
+const BLUR_RADIUS: isize = 2;
+
+/// Edge map of `img` by the Canny edge detector: Gaussian blur, Sobel gradients,
+/// non-maximum suppression and hysteresis. Gradients are scaled to luminance steps
+/// between neighbouring pixels, so both thresholds lie in [0, 255]. Pixels above
+/// `high_threshold` are edges, and so are pixels above `low_threshold` connected to them.
+pub(crate) fn canny_edges(
+    img: &ColorImage,
+    low_threshold: f64,
+    high_threshold: f64,
+) -> BinaryImage {
+    let (width, height) = (img.width, img.height);
+    let mut edges = BinaryImage::new_w_h(width, height);
+    if width == 0 || height == 0 {
+        return edges;
+    }
+    let gray: Vec<f64> = (0..width * height)
+        .map(|i| {
+            let pixel = img.get_pixel(i % width, i / width);
+            luminance(pixel.r, pixel.g, pixel.b) as f64
+        })
+        .collect();
+    let blurred = gaussian_blur(&gray, width, height);
+    let (magnitude, direction) = sobel(&blurred, width, height);
+    let thin = non_maximum_suppression(&magnitude, &direction, width, height);
+
+    // Grow the strong edges into the weak ones they touch
+    let mut stack: Vec<usize> = (0..thin.len())
+        .filter(|&i| thin[i] >= high_threshold)
+        .collect();
+    let mut is_edge = vec![false; thin.len()];
+    for &i in &stack {
+        is_edge[i] = true;
+    }
+    while let Some(i) = stack.pop() {
+        let (x, y) = ((i % width) as isize, (i / width) as isize);
+        for (dx, dy) in NEIGHBOURS {
+            let (nx, ny) = (x + dx, y + dy);
+            if nx < 0 || ny < 0 || nx >= width as isize || ny >= height as isize {
+                continue;
+            }
+            let j = ny as usize * width + nx as usize;
+            if !is_edge[j] && thin[j] >= low_threshold && thin[j] > 0.0 {
+                is_edge[j] = true;
+                stack.push(j);
+            }
+        }
+    }
+    for (i, _) in is_edge.iter().enumerate().filter(|(_, &edge)| edge) {
+        edges.set_pixel(i % width, i / width, true);
+    }
+    edges
+}
+
+const NEIGHBOURS: [(isize, isize); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// Value at (x, y), with the edge pixels repeated beyond the borders
+fn clamped(values: &[f64], width: usize, height: usize, x: isize, y: isize) -> f64 {
+    let x = x.clamp(0, width as isize - 1) as usize;
+    let y = y.clamp(0, height as isize - 1) as usize;
+    values[y * width + x]
+}
+
+/// Separable Gaussian blur, horizontally and then vertically
+fn gaussian_blur(values: &[f64], width: usize, height: usize) -> Vec<f64> {
+    let kernel: Vec<f64> = (-BLUR_RADIUS..=BLUR_RADIUS)
+        .map(|d| (-((d * d) as f64) / (2.0 * BLUR_SIGMA * BLUR_SIGMA)).exp())
+        .collect();
+    let sum: f64 = kernel.iter().sum();
+
+    let convolve = |values: &[f64], (dx, dy): (isize, isize)| -> Vec<f64> {
+        (0..width * height)
+            .map(|i| {
+                let (x, y) = ((i % width) as isize, (i / width) as isize);
+                (-BLUR_RADIUS..=BLUR_RADIUS)
+                    .zip(&kernel)
+                    .map(|(d, k)| k * clamped(values, width, height, x + d * dx, y + d * dy))
+                    .sum::<f64>()
+                    / sum
+            })
+            .collect()
+    };
+    convolve(&convolve(values, (1, 0)), (0, 1))
+}
+
+/// Gradient magnitude and direction (radians) by the Sobel operator
+fn sobel(values: &[f64], width: usize, height: usize) -> (Vec<f64>, Vec<f64>) {
+    let mut magnitude = vec![0.0; values.len()];
+    let mut direction = vec![0.0; values.len()];
+    for y in 0..height as isize {
+        for x in 0..width as isize {
+            let v = |dx: isize, dy: isize| clamped(values, width, height, x + dx, y + dy);
+            let gx = v(1, -1) + 2.0 * v(1, 0) + v(1, 1) - v(-1, -1) - 2.0 * v(-1, 0) - v(-1, 1);
+            let gy = v(-1, 1) + 2.0 * v(0, 1) + v(1, 1) - v(-1, -1) - 2.0 * v(0, -1) - v(1, -1);
+            let i = y as usize * width + x as usize;
+            // The kernels weigh 4 pixel differences
+            magnitude[i] = gx.hypot(gy) / 4.0;
+            direction[i] = gy.atan2(gx);
+        }
+    }
+    (magnitude, direction)
+}
+
+/// Keep only the pixels whose magnitude peaks across the edge, zeroing the rest
+fn non_maximum_suppression(
+    magnitude: &[f64],
+    direction: &[f64],
+    width: usize,
+    height: usize,
+) -> Vec<f64> {
+    let mut thin = vec![0.0; magnitude.len()];
+    for y in 0..height as isize {
+        for x in 0..width as isize {
+            let i = y as usize * width + x as usize;
+            // The gradient direction rounded to one of 4 axes
+            let angle = direction[i].to_degrees().rem_euclid(180.0);
+            let (dx, dy) = if !(22.5..157.5).contains(&angle) {
+                (1, 0)
+            } else if angle < 67.5 {
+                (1, 1)
+            } else if angle < 112.5 {
+                (0, 1)
+            } else {
+                (-1, 1)
+            };
+            let before = clamped(magnitude, width, height, x - dx, y - dy);
+            let after = clamped(magnitude, width, height, x + dx, y + dy);
+            if magnitude[i] >= before && magnitude[i] >= after {
+                thin[i] = magnitude[i];
+            }
+        }
+    }
+    thin
+}
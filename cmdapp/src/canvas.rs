@@ -0,0 +1,159 @@
+use std::fmt;
+use visioncortex::{Color, PointF64};
+
+use super::config::BlendMode;
+use super::svg::{escape_xml, SvgFile};
+use super::vector::{hex_string, number, PathSegment, VectorPath};
+
+/// HTML page drawing the paths of an `SvgFile` on a `<canvas>` with inline JavaScript,
+/// one call per path command, for debugging and demos. The canvas has the size of the SVG,
+/// margin and scale included, and the paths are painted as in the SVG, except that the
+/// corners of polygons are not rounded.
+pub struct CanvasFile<'a> {
+    svg: &'a SvgFile,
+    title: Option<String>,
+}
+
+impl<'a> CanvasFile<'a> {
+    pub fn new(svg: &'a SvgFile) -> Self {
+        Self { svg, title: None }
+    }
+
+    /// Set the title of the page
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+}
+
+impl fmt::Display for CanvasFile<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let svg = self.svg;
+        let margin = svg.viewbox_margin as f64;
+        let (width, height) = (
+            (svg.width as f64 + 2.0 * margin) * svg.scale,
+            (svg.height as f64 + 2.0 * margin) * svg.scale,
+        );
+        writeln!(f, "<!DOCTYPE html>")?;
+        writeln!(
+            f,
+            "<!-- Generator: visioncortex VTracer {} -->",
+            env!("CARGO_PKG_VERSION")
+        )?;
+        writeln!(f, "<html>")?;
+        writeln!(f, "<head>")?;
+        writeln!(f, r#"<meta charset="utf-8">"#)?;
+        let title = svg.title.as_deref().or(self.title.as_deref());
+        writeln!(
+            f,
+            "<title>{}</title>",
+            escape_xml(title.unwrap_or("VTracer"))
+        )?;
+        writeln!(f, "</head>")?;
+        writeln!(f, "<body>")?;
+        write!(
+            f,
+            r#"<canvas id="vtracer" width="{}" height="{}""#,
+            width.ceil(),
+            height.ceil()
+        )?;
+        // Applied to the canvas as a whole, so overlapping paths do not show through each other
+        if svg.opacity < 1.0 {
+            write!(f, r#" style="opacity:{}""#, svg.opacity)?;
+        }
+        writeln!(f, "></canvas>")?;
+        writeln!(f, "<script>")?;
+        writeln!(
+            f,
+            r#"const ctx = document.getElementById("vtracer").getContext("2d");"#
+        )?;
+
+        if let Some(color) = &svg.background_color {
+            writeln!(f, "ctx.fillStyle = \"{}\";", color_string(color))?;
+            writeln!(f, "ctx.fillRect(0, 0, {}, {});", width, height)?;
+        }
+        // The same transformations as the SVG, from the outermost
+        if let Some((x, y)) = svg.translate {
+            writeln!(f, "ctx.translate({}, {});", number(x), number(y))?;
+        }
+        if svg.scale != 1.0 {
+            writeln!(f, "ctx.scale({}, {});", svg.scale, svg.scale)?;
+        }
+        if margin > 0.0 {
+            writeln!(f, "ctx.translate({}, {});", margin, margin)?;
+        }
+        if svg.clip_to_bounds {
+            writeln!(f, "ctx.beginPath();")?;
+            writeln!(f, "ctx.rect(0, 0, {}, {});", svg.width, svg.height)?;
+            writeln!(f, "ctx.clip();")?;
+        }
+        if let Some(mode) = svg.blend_mode {
+            let operation = match mode {
+                BlendMode::Normal => "source-over",
+                BlendMode::Multiply => "multiply",
+                BlendMode::Screen => "screen",
+                BlendMode::Overlay => "overlay",
+            };
+            writeln!(f, "ctx.globalCompositeOperation = \"{}\";", operation)?;
+        }
+        if let Some(width) = svg.stroke_width {
+            // The SVG strokes in currentColor, which is black
+            writeln!(f, "ctx.strokeStyle = \"#000000\";")?;
+            writeln!(f, "ctx.lineWidth = {};", number(width))?;
+        }
+
+        for path in VectorPath::from_svg(svg) {
+            writeln!(f, "ctx.beginPath();")?;
+            for segment in &path.segments {
+                write_segment(f, segment)?;
+            }
+            // Both use the nonzero winding rule, the default of SVG and canvas alike
+            if let Some(fill) = path.fill {
+                writeln!(f, "ctx.fillStyle = \"{}\";", hex_string(fill))?;
+                writeln!(f, "ctx.fill();")?;
+            }
+            if path.stroke.is_some() {
+                writeln!(f, "ctx.stroke();")?;
+            }
+        }
+
+        writeln!(f, "</script>")?;
+        writeln!(f, "</body>")?;
+        writeln!(f, "</html>")
+    }
+}
+
+fn write_segment(f: &mut fmt::Formatter, segment: &PathSegment) -> fmt::Result {
+    let xy = |point: &PointF64| format!("{}, {}", number(point.x), number(point.y));
+    match segment {
+        PathSegment::MoveTo(point) => writeln!(f, "ctx.moveTo({});", xy(point)),
+        PathSegment::LineTo(point) => writeln!(f, "ctx.lineTo({});", xy(point)),
+        PathSegment::CubicBezierTo {
+            control1,
+            control2,
+            end,
+        } => writeln!(
+            f,
+            "ctx.bezierCurveTo({}, {}, {});",
+            xy(control1),
+            xy(control2),
+            xy(end)
+        ),
+        PathSegment::ClosePath => writeln!(f, "ctx.closePath();"),
+    }
+}
+
+/// The background keeps its alpha, as the SVG does with `fill-opacity`
+fn color_string(color: &Color) -> String {
+    if color.a == 255 {
+        color.to_hex_string()
+    } else {
+        format!(
+            "rgba({}, {}, {}, {})",
+            color.r,
+            color.g,
+            color.b,
+            number(color.a as f64 / 255.0)
+        )
+    }
+}
@@ -0,0 +1,315 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use image::{DynamicImage, GenericImageView};
+
+use super::config::Preset;
+use super::converter::luminance;
+use super::decoder::decode_image;
+
+/// Number of samples taken along the longer side of the image
+const SAMPLE_SIZE: u32 = 128;
+/// Low bits dropped from each channel, as with the default `color_precision` of 6
+const QUANTIZE_SHIFT: u32 = 2;
+/// Share of the samples a color needs to count as a layer of its own
+const MIN_LAYER_SHARE: f64 = 0.005;
+/// Area in pixels below which patches are dropped, as with the default `filter_speckle` of 4
+const MIN_REGION_AREA: f64 = 16.0;
+/// Low bits dropped from each channel for the color entropy, leaving 4096 colors
+const ENTROPY_SHIFT: u32 = 4;
+/// Color entropy in bits from which an image is taken for a photo
+const PHOTO_ENTROPY: f64 = 6.0;
+/// Median saturation below which an image counts as grey
+const BW_SATURATION: f64 = 0.15;
+/// Share of edge samples from which a grey image counts as line art
+const BW_EDGE_DENSITY: f64 = 0.02;
+/// Laplacian of the luminance above which a sample lies on an edge
+const EDGE_LAPLACIAN: f64 = 64.0;
+
+/// Rough prediction of the work a conversion will take, see `estimate_complexity`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ComplexityReport {
+    /// Number of colors covering a noticeable share of the image
+    pub estimated_layers: usize,
+    /// Number of connected regions of one color, each of which becomes a path
+    pub estimated_paths: usize,
+    /// Estimated length of the boundaries between regions in the full size image, in
+    /// thousands of pixels. Conversion time and output size grow about linearly with it.
+    pub complexity_score: f32,
+}
+
+/// Estimate the complexity of converting `image` with the default settings, from the color
+/// histogram and edge density of a grid of at most 128 by 128 samples. The cost does not
+/// depend on the image size, so it takes well under a millisecond on any input.
+/// Fine detail between the samples is missed, so the estimates are lower bounds on photos.
+pub fn estimate_complexity(image: &DynamicImage) -> ComplexityReport {
+    let Some(grid) = SampleGrid::new(image) else {
+        return ComplexityReport::default();
+    };
+    let (columns, rows, step) = (grid.columns, grid.rows, grid.step);
+    // Quantized colors
+    let samples: Vec<Option<[u8; 3]>> = grid
+        .samples
+        .iter()
+        .map(|sample| sample.map(|color| color.map(|c| c >> QUANTIZE_SHIFT)))
+        .collect();
+
+    let mut histogram = HashMap::new();
+    for color in samples.iter().flatten() {
+        *histogram.entry(*color).or_insert(0usize) += 1;
+    }
+    let min_count = (samples.len() as f64 * MIN_LAYER_SHARE).ceil() as usize;
+    let estimated_layers = histogram
+        .values()
+        .filter(|&&count| count >= min_count)
+        .count();
+
+    let mut edges = 0;
+    for row in 0..rows {
+        for column in 0..columns {
+            let color = samples[row * columns + column];
+            if column + 1 < columns && samples[row * columns + column + 1] != color {
+                edges += 1;
+            }
+            if row + 1 < rows && samples[(row + 1) * columns + column] != color {
+                edges += 1;
+            }
+        }
+    }
+
+    ComplexityReport {
+        estimated_layers,
+        estimated_paths: count_regions(&samples, columns, rows, MIN_REGION_AREA / (step * step)),
+        // Every edge between two samples stands for `step` pixels of boundary
+        complexity_score: (edges as f64 * step / 1000.0) as f32,
+    }
+}
+
+/// Choose between the `Bw`, `Poster` and `Photo` presets for `image`, from the same grid of
+/// samples as `estimate_complexity`. Images of many colors are photos, whatever their
+/// saturation. Of the others, grey ones with many edges are line art, and the rest are
+/// posters. Grey means a low median saturation both over the image and outside its most
+/// common color, so colored shapes on a white background are not taken for line art.
+pub fn select_preset(image: &DynamicImage) -> Preset {
+    let Some(grid) = SampleGrid::new(image) else {
+        return Preset::Poster;
+    };
+    let opaque: Vec<[u8; 3]> = grid.samples.iter().flatten().copied().collect();
+    if opaque.is_empty() {
+        return Preset::Poster;
+    }
+
+    let mut histogram = HashMap::new();
+    for color in &opaque {
+        *histogram
+            .entry(color.map(|c| c >> ENTROPY_SHIFT))
+            .or_insert(0usize) += 1;
+    }
+    let entropy: f64 = histogram
+        .values()
+        .map(|&count| {
+            let p = count as f64 / opaque.len() as f64;
+            -p * p.log2()
+        })
+        .sum();
+
+    // Line art is mostly background, so its lines are measured apart from it too
+    let background = histogram
+        .iter()
+        .max_by_key(|(_, &count)| count)
+        .map(|(&color, _)| color);
+    let foreground: Vec<[u8; 3]> = opaque
+        .iter()
+        .filter(|color| Some(color.map(|c| c >> ENTROPY_SHIFT)) != background)
+        .copied()
+        .collect();
+    let median_saturation = median_saturation(&opaque).max(median_saturation(&foreground));
+
+    log::debug!(
+        "Color entropy {:.2} bits, median saturation {:.2}, edge density {:.3}",
+        entropy,
+        median_saturation,
+        grid.edge_density()
+    );
+    if entropy >= PHOTO_ENTROPY {
+        Preset::Photo
+    } else if median_saturation < BW_SATURATION && grid.edge_density() >= BW_EDGE_DENSITY {
+        Preset::Bw
+    } else {
+        Preset::Poster
+    }
+}
+
+/// Median of the HSV saturations of `colors`, 0 if there are none
+fn median_saturation(colors: &[[u8; 3]]) -> f64 {
+    let mut saturations: Vec<f64> = colors
+        .iter()
+        .map(|color| {
+            let max = *color.iter().max().unwrap();
+            let min = *color.iter().min().unwrap();
+            if max == 0 {
+                0.0
+            } else {
+                (max - min) as f64 / max as f64
+            }
+        })
+        .collect();
+    saturations.sort_by(f64::total_cmp);
+    saturations
+        .get(saturations.len() / 2)
+        .copied()
+        .unwrap_or_default()
+}
+
+/// `select_preset` for the image file at `path`, decoded as for a conversion
+pub fn select_preset_for_file(path: &Path) -> Result<Preset, String> {
+    let buffer = fs::read(path).map_err(|_| String::from("Cannot read input image."))?;
+    Ok(select_preset(&decode_image(&buffer, Some(path))?))
+}
+
+/// Colors sampled at the centers of at most 128 by 128 cells covering the image
+struct SampleGrid {
+    /// Row by row, with transparent pixels as None
+    samples: Vec<Option<[u8; 3]>>,
+    columns: usize,
+    rows: usize,
+    /// Size of a cell, in pixels
+    step: f64,
+}
+
+impl SampleGrid {
+    fn new(image: &DynamicImage) -> Option<Self> {
+        let (width, height) = image.dimensions();
+        if width == 0 || height == 0 {
+            return None;
+        }
+        let step = (width.max(height) as f64 / SAMPLE_SIZE as f64).max(1.0);
+        let columns = ((width as f64 / step) as usize).max(1);
+        let rows = ((height as f64 / step) as usize).max(1);
+        let samples = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| {
+                let x = ((column as f64 + 0.5) * step) as u32;
+                let y = ((row as f64 + 0.5) * step) as u32;
+                let pixel = image.get_pixel(x.min(width - 1), y.min(height - 1)).0;
+                if pixel[3] == 0 {
+                    None
+                } else {
+                    Some([pixel[0], pixel[1], pixel[2]])
+                }
+            })
+            .collect();
+        Some(Self {
+            samples,
+            columns,
+            rows,
+            step,
+        })
+    }
+
+    /// Share of the samples where the Laplacian of the luminance exceeds `EDGE_LAPLACIAN`.
+    /// Transparent samples count as white, and the samples on the border are not tested.
+    fn edge_density(&self) -> f64 {
+        if self.columns < 3 || self.rows < 3 {
+            return 0.0;
+        }
+        let luma = |column: usize, row: usize| match self.samples[row * self.columns + column] {
+            Some([r, g, b]) => luminance(r, g, b) as f64,
+            None => 255.0,
+        };
+        let mut edges = 0;
+        for row in 1..self.rows - 1 {
+            for column in 1..self.columns - 1 {
+                let laplacian = 4.0 * luma(column, row)
+                    - luma(column - 1, row)
+                    - luma(column + 1, row)
+                    - luma(column, row - 1)
+                    - luma(column, row + 1);
+                if laplacian.abs() > EDGE_LAPLACIAN {
+                    edges += 1;
+                }
+            }
+        }
+        edges as f64 / ((self.columns - 2) * (self.rows - 2)) as f64
+    }
+}
+
+/// Number of 4-connected regions of one opaque color made of at least `min_samples` samples
+fn count_regions(
+    samples: &[Option<[u8; 3]>],
+    columns: usize,
+    rows: usize,
+    min_samples: f64,
+) -> usize {
+    let mut visited = vec![false; samples.len()];
+    let mut regions = 0;
+    let mut stack = vec![];
+    for start in 0..samples.len() {
+        if visited[start] || samples[start].is_none() {
+            continue;
+        }
+        let mut size = 0;
+        visited[start] = true;
+        stack.push(start);
+        while let Some(index) = stack.pop() {
+            size += 1;
+            let (column, row) = (index % columns, index / columns);
+            let neighbours = [
+                (column > 0).then(|| index - 1),
+                (column + 1 < columns).then(|| index + 1),
+                (row > 0).then(|| index - columns),
+                (row + 1 < rows).then(|| index + columns),
+            ];
+            for neighbour in neighbours.into_iter().flatten() {
+                if !visited[neighbour] && samples[neighbour] == samples[index] {
+                    visited[neighbour] = true;
+                    stack.push(neighbour);
+                }
+            }
+        }
+        if size as f64 >= min_samples {
+            regions += 1;
+        }
+    }
+    regions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbImage;
+
+    fn image(pixel: impl Fn(u32, u32) -> [u8; 3]) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(256, 256, |x, y| image::Rgb(pixel(x, y))))
+    }
+
+    #[test]
+    fn flat_colors_are_posters() {
+        let colors = [[230, 40, 40], [40, 160, 60], [30, 60, 200], [250, 210, 30]];
+        let img = image(|x, y| colors[(x / 128 + y / 128 * 2) as usize]);
+        assert_eq!(select_preset(&img), Preset::Poster);
+    }
+
+    #[test]
+    fn noise_is_a_photo() {
+        let rng = fastrand::Rng::with_seed(1);
+        let pixels: Vec<[u8; 3]> = (0..256 * 256)
+            .map(|_| [rng.u8(..), rng.u8(..), rng.u8(..)])
+            .collect();
+        let img = image(|x, y| pixels[(y * 256 + x) as usize]);
+        assert_eq!(select_preset(&img), Preset::Photo);
+    }
+
+    #[test]
+    fn black_lines_on_white_are_line_art() {
+        let img = image(|x, y| {
+            if x % 16 < 2 || y % 16 < 2 {
+                [0, 0, 0]
+            } else {
+                [255, 255, 255]
+            }
+        });
+        assert_eq!(select_preset(&img), Preset::Bw);
+    }
+}
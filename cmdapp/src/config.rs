@@ -1,49 +1,626 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
-use visioncortex::PathSimplifyMode;
 
+use super::builder::{ConfigBuilder, ConverterConfigBuilder};
+use super::error::VTracerError;
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum Preset {
     Bw,
     Poster,
     Photo,
+    /// Line art and technical drawings, traced as thin stroked polygons
+    Line,
+    /// A user defined preset, typically loaded with `Config::load_toml`
+    Custom(Box<Config>),
+    /// One of `Bw`, `Poster` and `Photo`, chosen from the image by `select_preset`.
+    /// Without an image to inspect, `Config::from_preset` gives the default config.
+    Auto,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum ColorMode {
     Color,
+    #[cfg_attr(feature = "serde", serde(alias = "bw"))]
     Binary,
+    /// Like `Color`, but on the luminance of the image, so every layer is a shade of grey
+    #[cfg_attr(feature = "serde", serde(alias = "gray", alias = "grey"))]
+    Grayscale,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum Hierarchical {
+    /// Shapes are stacked on top of each other, so lower layers extend below upper ones
     Stacked,
+    /// Shapes are disjoint: the image is re-clustered after flattening, so every layer is
+    /// traced with the holes left by its neighbours. Pixel outlines cover every pixel exactly
+    /// once; simplified paths smooth each side of a shared edge on its own, so neighbours may
+    /// overlap or part by a fraction of a pixel along it.
     Cutout,
 }
 
-/// Converter config
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum OutputFormat {
+    #[default]
+    Svg,
+    Dxf,
+    Pdf,
+    /// Encapsulated PostScript
+    Eps,
+    /// Gzip compressed SVG
+    #[cfg_attr(feature = "serde", serde(alias = "svgz"))]
+    SvgCompressed,
+    /// PNG of the outlines in red over the input image, to check a trace
+    #[cfg_attr(feature = "serde", serde(alias = "png-trace"))]
+    PngTrace,
+    /// HTML page drawing the paths on a `<canvas>` with JavaScript
+    Canvas,
+    /// Rust source of the paths as constants, with the types of the `vtracer-runtime` crate
+    Rust,
+    /// Array of `{"fill":"#RRGGBB","d":"M... Z"}` objects
+    Json,
+}
+
+/// Curve fitting mode. The first three are the modes of `visioncortex::PathSimplifyMode`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum PathSimplifyMode {
+    /// Pixel outlines
+    #[cfg_attr(feature = "serde", serde(alias = "pixel"))]
+    None,
+    /// Straight lines only, whatever the corner threshold
+    Polygon,
+    Spline,
+    /// Cubic Bézier curves fitted to the polygons within `Config::bezier_tolerance`
+    Bezier,
+}
+
+/// Whether paths are drawn as filled regions or as stroked outlines
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum StrokeMode {
+    #[default]
+    Filled,
+    /// Only supported in `ColorMode::Binary`
+    Stroked,
+}
+
+/// An sRGB color with alpha, written as `RRGGBB` or `RRGGBBAA` hex digits
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "String", into = "String"))]
+pub struct Rgba {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+/// Unit of the SVG `width` and `height`, converted from pixels at 96 dpi
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Unit {
+    Px,
+    Mm,
+    Cm,
+    In,
+}
+
+/// Width to height ratio of the image, written as `W:H`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "String", into = "String"))]
+pub struct AspectRatio {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A rectangle of pixels, written as `X,Y,W,H` from the top left corner
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "String", into = "String"))]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// How the image is brought to `Config::aspect_ratio`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum AspectRatioFit {
+    /// Extend the canvas, filled with the background color or transparency
+    #[default]
+    Pad,
+    /// Cut the excess off both sides, keeping the center
+    Crop,
+}
+
+/// Order of the paths in the output
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum PathSort {
+    /// Largest enclosed area first, so smaller paths are painted over larger ones
+    #[default]
+    Area,
+    /// By hue, from red through green and blue, with grays first
+    Color,
+    /// In the order the paths were traced
+    None,
+}
+
+/// CSS `mix-blend-mode` of the layers, for compositing in design tools
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum BlendMode {
+    Normal,
+    Multiply,
+    Screen,
+    Overlay,
+}
+
+/// Color space the pixels are clustered in
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ColorSpace {
+    #[default]
+    Rgb,
+    /// CIELAB, where distances follow perceived color differences more closely
+    Lab,
+}
+
+/// Transfer function of the input pixels
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum InputColorspace {
+    #[default]
+    Srgb,
+    /// Linear light, as some scientific images without an sRGB chunk are stored
+    Linear,
+}
+
+/// Character encoding of the SVG output
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum OutputEncoding {
+    #[default]
+    Utf8,
+    /// ISO-8859-1, for legacy consumers. Characters outside of it are written as
+    /// numeric character references.
+    Latin1,
+}
+
+/// Name of a `Config` field, as serialized
+pub type FieldName = String;
+
+/// Converter config.
+/// Serialized field names are the Rust ones; the CLI long option names are accepted as aliases.
+/// Compared field by field; the floating point fields rule out `Eq` and `Hash`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Config {
+    #[cfg_attr(feature = "serde", serde(alias = "colormode"))]
     pub color_mode: ColorMode,
     pub hierarchical: Hierarchical,
+    /// Discard patches smaller than this many pixels squared, i.e. `filter_speckle` is the side
+    /// of the smallest square patch kept
     pub filter_speckle: usize,
+    /// Discard patches of fewer pixels than this, in place of `filter_speckle` when set
+    #[cfg_attr(feature = "serde", serde(alias = "min_area", alias = "min-area"))]
+    pub filter_speckle_area_direct: Option<f64>,
     pub color_precision: i32,
+    #[cfg_attr(feature = "serde", serde(alias = "gradient_step"))]
     pub layer_difference: i32,
+    /// Color space the pixels are clustered in. Paths are colored in sRGB either way.
+    #[cfg_attr(feature = "serde", serde(default, alias = "color-space"))]
+    pub color_space: ColorSpace,
+    /// Encoding of the input pixels. Linear light is gamma encoded to sRGB before quantization.
+    #[cfg_attr(feature = "serde", serde(default, alias = "input-colorspace"))]
+    pub input_colorspace: InputColorspace,
     pub mode: PathSimplifyMode,
     pub corner_threshold: i32,
+    #[cfg_attr(feature = "serde", serde(alias = "segment_length"))]
     pub length_threshold: f64,
+    /// Number of smoothing iterations per path. Higher values improve accuracy at a linear time cost
     pub max_iterations: usize,
     pub splice_threshold: i32,
     pub path_precision: Option<u32>,
+    /// Radius of the arcs that replace every polygon corner in `PathSimplifyMode::Polygon`,
+    /// in SVG user units. Corners too tight for the radius get the largest arc that fits.
+    #[cfg_attr(feature = "serde", serde(default, alias = "corner-rounding"))]
+    pub corner_rounding: f64,
+    /// Maximum distance in px between a fitted curve and the traced outline in `PathSimplifyMode::Bezier`
+    #[cfg_attr(
+        feature = "serde",
+        serde(default = "default_bezier_tolerance", alias = "bezier-tolerance")
+    )]
+    pub bezier_tolerance: f64,
+    /// Merge the closest colors until at most this many distinct fill colors remain
+    #[cfg_attr(feature = "serde", serde(alias = "max-colors"))]
+    pub max_colors: Option<usize>,
+    /// Keep at most this many color layers, merging the smallest into their neighbours
+    #[cfg_attr(feature = "serde", serde(alias = "layer-limit"))]
+    pub layer_limit: Option<usize>,
+    /// Keep only the largest paths of each color, so noisy regions do not fragment into
+    /// hundreds of small paths of one color
+    #[cfg_attr(
+        feature = "serde",
+        serde(alias = "max-paths-per-cluster", alias = "max-colors-per-layer")
+    )]
+    pub max_paths_per_cluster: Option<usize>,
+    /// Snap every path color to the closest color of this palette, a GIMP `.gpl` file
+    /// or a text file of one `#RRGGBB` color per line
+    #[cfg_attr(
+        feature = "serde",
+        serde(alias = "color_palette", alias = "color-palette")
+    )]
+    pub palette_file: Option<PathBuf>,
+    /// Snap the blended pixels along anti-aliased edges to one of the two colors they blend
+    #[cfg_attr(feature = "serde", serde(default, alias = "anti-alias"))]
+    pub anti_alias: bool,
+    /// Invert the color channels of the image, keeping alpha, before clustering
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub invert: bool,
+    /// Composite the image over white before clustering, so transparent areas are traced
+    /// as white instead of being left out
+    #[cfg_attr(feature = "serde", serde(default, alias = "ignore-alpha"))]
+    pub ignore_alpha: bool,
+    /// Make pixels of lower alpha fully transparent before clustering, and leave every
+    /// transparent pixel out of the paths. 0 keeps semi-transparent pixels as they are.
+    #[cfg_attr(feature = "serde", serde(default, alias = "alpha-threshold"))]
+    pub alpha_threshold: u8,
+    /// Gamma applied to the luminance in `ColorMode::Grayscale` and `ColorMode::Binary`.
+    /// Below 1 brightens shadows, above 1 darkens them.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default = "default_grayscale_gamma", alias = "grayscale-gamma")
+    )]
+    pub grayscale_gamma: f64,
+    /// Weights of red, green and blue in the luminance of `ColorMode::Grayscale` and
+    /// `ColorMode::Binary`, summing to 1. Rec. 601 by default; Rec. 709 is
+    /// `(0.2126, 0.7152, 0.0722)`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default = "default_grayscale_weights", alias = "grayscale-weights")
+    )]
+    pub grayscale_weights: (f64, f64, f64),
+    /// In `ColorMode::Binary`, pixels of a luminance below this become black, the others white
+    #[cfg_attr(
+        feature = "serde",
+        serde(default = "default_binary_threshold", alias = "threshold")
+    )]
+    pub binary_threshold: u8,
+    /// Pick the binary threshold with Otsu's method instead of using `binary_threshold`
+    #[cfg_attr(feature = "serde", serde(default, alias = "otsu"))]
+    pub binary_threshold_otsu: bool,
+    /// Diffuse the error of the binary threshold to the neighbouring pixels (Floyd-Steinberg),
+    /// so gradients become dot patterns as in 1-bit printing instead of flat bands
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub dither: bool,
+    /// In `ColorMode::Binary`, trace the edges found by the Canny edge detector instead of
+    /// thresholding the luminance. Cleaner than thresholding on photographs.
+    #[cfg_attr(feature = "serde", serde(default, alias = "canny"))]
+    pub canny_preprocess: bool,
+    /// Gradient, in luminance steps between neighbouring pixels, above which pixels next to
+    /// an edge are part of it
+    #[cfg_attr(
+        feature = "serde",
+        serde(default = "default_canny_low_threshold", alias = "canny-low-threshold")
+    )]
+    pub canny_low_threshold: f64,
+    /// Gradient, in luminance steps between neighbouring pixels, above which pixels are edges
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            default = "default_canny_high_threshold",
+            alias = "canny-high-threshold"
+        )
+    )]
+    pub canny_high_threshold: f64,
+    /// Discard paths whose total arc length is below this many pixels
+    #[cfg_attr(feature = "serde", serde(alias = "min-path-length"))]
+    pub min_path_length: Option<f64>,
+    /// Join open subpaths of one color whose ends are at most this many pixels apart
+    #[cfg_attr(
+        feature = "serde",
+        serde(default = "default_path_join_tolerance", alias = "path-join-tolerance")
+    )]
+    pub path_join_tolerance: f64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub stroke_mode: StrokeMode,
+    /// Width of the outlines in `StrokeMode::Stroked`, 1 if not set
+    #[cfg_attr(feature = "serde", serde(alias = "stroke-width"))]
+    pub stroke_width: Option<f64>,
+    #[cfg_attr(feature = "serde", serde(alias = "background-color"))]
+    pub background_color: Option<Rgba>,
+    /// Opacity of the whole drawing, background included, in [0, 1]. Set on a root `<g>`
+    /// when below 1, to lay the SVG over another image.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default = "default_global_opacity", alias = "opacity")
+    )]
+    pub global_opacity: f64,
+    /// Written as `mix-blend-mode` on every layer, or on every path without `layer_names`
+    #[cfg_attr(feature = "serde", serde(alias = "blend-mode"))]
+    pub blend_mode: Option<BlendMode>,
+    /// Downscale the image, keeping its aspect ratio, to fit within this width and height
+    /// before anything else, so `region` is in pixels of the downscaled image. Smaller
+    /// images are left as they are.
+    #[cfg_attr(feature = "serde", serde(alias = "resize-to"))]
+    pub resize_to: Option<(u32, u32)>,
+    /// Convert only this part of the image, which must lie within it
+    pub region: Option<Rect>,
+    /// Pad or crop the image to this aspect ratio before conversion
+    #[cfg_attr(feature = "serde", serde(alias = "aspect-ratio"))]
+    pub aspect_ratio: Option<AspectRatio>,
+    #[cfg_attr(feature = "serde", serde(default, alias = "aspect-ratio-fit"))]
+    pub aspect_ratio_fit: AspectRatioFit,
+    /// Padding around the SVG viewBox, with the paths shifted to keep them centered
+    #[cfg_attr(feature = "serde", serde(default, alias = "viewbox-margin"))]
+    pub viewbox_margin: u32,
+    /// Offset of the paths in user units, set as a `transform` on the group holding them.
+    /// The viewBox and the background stay in place.
+    pub translate: Option<(f64, f64)>,
+    /// Clip the paths to the image rectangle, hiding control points that overshoot it
+    #[cfg_attr(feature = "serde", serde(default, alias = "clip-to-bounds"))]
+    pub clip_to_bounds: bool,
+    /// Factor applied to the SVG dimensions and path coordinates, e.g. 3.7795275591 for mm at 96 dpi
+    #[cfg_attr(feature = "serde", serde(default = "default_scale"))]
+    pub scale: f64,
+    /// Write the SVG size in this unit, with a viewBox in pixels
+    pub unit: Option<Unit>,
+    /// Give the SVG the physical size of a PNG input with a resolution, in millimeters
+    /// unless `unit` is set. Only applies when converting files and streams.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default = "default_preserve_dpi", alias = "preserve-dpi")
+    )]
+    pub preserve_dpi: bool,
+    /// Wrap every path in a `<g>` layer with an id and an Inkscape label naming its color
+    #[cfg_attr(feature = "serde", serde(default, alias = "layer-names"))]
+    pub layer_names: bool,
+    /// Put before the `id` of every element, as in `vt-layer-1`, so the ids of several SVGs
+    /// embedded in one HTML page do not clash. Empty for ids without a prefix.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default = "default_output_id_prefix", alias = "id-prefix")
+    )]
+    pub output_id_prefix: String,
+    /// Embed the input image as a hidden base64 PNG `<image>` below the paths
+    #[cfg_attr(feature = "serde", serde(default, alias = "embed-original"))]
+    pub embed_original: bool,
+    /// Written as the `<title>` of the SVG, for screen readers
+    #[cfg_attr(feature = "serde", serde(alias = "svg-title"))]
+    pub svg_title: Option<String>,
+    /// Written as the `<desc>` of the SVG, for screen readers
+    #[cfg_attr(feature = "serde", serde(alias = "svg-desc"))]
+    pub svg_desc: Option<String>,
+    /// Merge paths and deduplicate attributes in the output
+    #[cfg_attr(feature = "serde", serde(default, alias = "optimize-svg"))]
+    pub optimize_svg: bool,
+    /// Write paths of identical shape once, as a `<symbol>`, and place them with `<use>`
+    #[cfg_attr(feature = "serde", serde(default, alias = "dedupe-paths"))]
+    pub dedupe_paths: bool,
+    /// Order of the paths in the document, which renderers paint in. Stacked color layers
+    /// keep their order, and only the paths within one layer are sorted.
+    #[cfg_attr(feature = "serde", serde(default, alias = "sort-paths-by"))]
+    pub path_sort: PathSort,
+    /// End every subpath with `Z` in binary mode. When false, subpaths whose first and last
+    /// points are not adjacent are left open, so pen plotters lift the pen at their end.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default = "default_close_paths", alias = "close-paths")
+    )]
+    pub close_paths: bool,
+    /// Write every element, or every value of `OutputFormat::Json`, on its own line, indented
+    /// by this many spaces per nesting level
+    #[cfg_attr(feature = "serde", serde(alias = "output-indent"))]
+    pub output_indent: Option<u32>,
+    /// Largest SVG to write, in bytes. Larger results are traced again with `filter_speckle`
+    /// raised by 1 until they fit. Measured on the SVG whatever the output format.
+    #[cfg_attr(
+        feature = "serde",
+        serde(alias = "max-output-bytes", alias = "max-file-size")
+    )]
+    pub max_output_bytes: Option<usize>,
+    #[cfg_attr(feature = "serde", serde(default, alias = "format"))]
+    pub output_format: OutputFormat,
+    #[cfg_attr(feature = "serde", serde(default, alias = "encoding"))]
+    pub output_encoding: OutputEncoding,
+    /// Write the paths of each color to a file of their own, named after the output file
+    /// with `_color_RRGGBB` appended, for cutting each color from its own sheet. Honoured
+    /// when converting files, `Hierarchical::Cutout` keeping the layers from overlapping.
+    #[cfg_attr(feature = "serde", serde(default, alias = "split-by-color"))]
+    pub split_by_color: bool,
+    /// Build the paths of color layers on multiple threads
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub parallel: bool,
+    /// Number of threads building paths with `parallel`, instead of one per CPU
+    #[cfg_attr(feature = "serde", serde(alias = "cpu-threads"))]
+    pub cpu_threads: Option<usize>,
+    /// Seed of the random colors tried for keying transparent pixels when none of 6 fixed
+    /// colors is free, the only random step of the pipeline; clustering and `max_colors`
+    /// are deterministic. Unset, every conversion draws a new seed.
+    #[cfg_attr(feature = "serde", serde(alias = "seed"))]
+    pub random_seed: Option<u64>,
 }
 
-pub(crate) struct ConverterConfig {
+/// Invokes `$callback!` with `$args`, then the name of every `Config` field
+macro_rules! config_fields {
+    ($callback:ident!($($args:tt)*)) => {
+        $callback!(
+            $($args)*;
+            color_mode,
+            hierarchical,
+            filter_speckle,
+            filter_speckle_area_direct,
+            color_precision,
+            layer_difference,
+            color_space,
+            input_colorspace,
+            mode,
+            corner_threshold,
+            length_threshold,
+            max_iterations,
+            splice_threshold,
+            path_precision,
+            corner_rounding,
+            bezier_tolerance,
+            max_colors,
+            layer_limit,
+            max_paths_per_cluster,
+            palette_file,
+            anti_alias,
+            invert,
+            ignore_alpha,
+            alpha_threshold,
+            grayscale_gamma,
+            grayscale_weights,
+            binary_threshold,
+            binary_threshold_otsu,
+            dither,
+            canny_preprocess,
+            canny_low_threshold,
+            canny_high_threshold,
+            min_path_length,
+            path_join_tolerance,
+            stroke_mode,
+            stroke_width,
+            background_color,
+            global_opacity,
+            blend_mode,
+            resize_to,
+            region,
+            aspect_ratio,
+            aspect_ratio_fit,
+            viewbox_margin,
+            translate,
+            clip_to_bounds,
+            scale,
+            unit,
+            preserve_dpi,
+            layer_names,
+            output_id_prefix,
+            embed_original,
+            svg_title,
+            svg_desc,
+            optimize_svg,
+            dedupe_paths,
+            path_sort,
+            close_paths,
+            output_indent,
+            max_output_bytes,
+            output_format,
+            output_encoding,
+            split_by_color,
+            parallel,
+            cpu_threads,
+            random_seed,
+        )
+    };
+}
+
+/// The settings the converter works with, in its own units. Usually made from a
+/// `Config`, or with `ConverterConfig::builder()` to give the values in these units directly.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConverterConfig {
     pub color_mode: ColorMode,
     pub hierarchical: Hierarchical,
+    /// Patches of fewer pixels than this are discarded
     pub filter_speckle_area: usize,
+    /// Number of low bits dropped from each color channel, in [0, 7]
     pub color_precision_loss: i32,
     pub layer_difference: i32,
+    pub color_space: ColorSpace,
+    pub input_colorspace: InputColorspace,
     pub mode: PathSimplifyMode,
+    /// In radians
     pub corner_threshold: f64,
     pub length_threshold: f64,
     pub max_iterations: usize,
+    /// In radians
     pub splice_threshold: f64,
     pub path_precision: Option<u32>,
+    pub bezier_tolerance: f64,
+    pub corner_rounding: f64,
+    pub max_colors: Option<usize>,
+    pub layer_limit: Option<usize>,
+    pub max_paths_per_cluster: Option<usize>,
+    pub palette_file: Option<PathBuf>,
+    pub anti_alias: bool,
+    pub invert: bool,
+    pub ignore_alpha: bool,
+    pub alpha_threshold: u8,
+    pub grayscale_gamma: f64,
+    pub grayscale_weights: (f64, f64, f64),
+    /// `None` when it is picked with Otsu's method
+    pub binary_threshold: Option<u8>,
+    pub dither: bool,
+    pub canny_preprocess: bool,
+    pub canny_low_threshold: f64,
+    pub canny_high_threshold: f64,
+    pub min_path_length: Option<f64>,
+    pub path_join_tolerance: f64,
+    /// Stroke width of the paths, or `None` if they are filled
+    pub stroke_width: Option<f64>,
+    pub background_color: Option<Rgba>,
+    pub global_opacity: f64,
+    pub blend_mode: Option<BlendMode>,
+    pub resize_to: Option<(u32, u32)>,
+    pub region: Option<Rect>,
+    pub aspect_ratio: Option<AspectRatio>,
+    pub aspect_ratio_fit: AspectRatioFit,
+    pub viewbox_margin: u32,
+    pub translate: Option<(f64, f64)>,
+    pub clip_to_bounds: bool,
+    pub scale: f64,
+    pub unit: Option<Unit>,
+    pub layer_names: bool,
+    pub output_id_prefix: String,
+    pub embed_original: bool,
+    pub svg_title: Option<String>,
+    pub svg_desc: Option<String>,
+    pub optimize_svg: bool,
+    pub dedupe_paths: bool,
+    pub path_sort: PathSort,
+    pub close_paths: bool,
+    pub output_indent: Option<u32>,
+    pub max_output_bytes: Option<usize>,
+    pub output_encoding: OutputEncoding,
+    pub parallel: bool,
+    pub cpu_threads: Option<usize>,
+    pub random_seed: Option<u64>,
+}
+
+impl Default for ConverterConfig {
+    fn default() -> Self {
+        Config::default().into()
+    }
+}
+
+impl ConverterConfig {
+    /// Build a converter config from the defaults, without range checks
+    pub fn builder() -> ConverterConfigBuilder {
+        ConverterConfigBuilder::new()
+    }
 }
 
 impl Default for Config {
@@ -53,13 +630,68 @@ impl Default for Config {
             hierarchical: Hierarchical::Stacked,
             mode: PathSimplifyMode::Spline,
             filter_speckle: 4,
+            filter_speckle_area_direct: None,
             color_precision: 6,
             layer_difference: 16,
+            color_space: ColorSpace::Rgb,
+            input_colorspace: InputColorspace::Srgb,
             corner_threshold: 60,
             length_threshold: 4.0,
             splice_threshold: 45,
             max_iterations: 10,
             path_precision: Some(2),
+            bezier_tolerance: 0.5,
+            corner_rounding: 0.0,
+            max_colors: None,
+            layer_limit: None,
+            max_paths_per_cluster: None,
+            palette_file: None,
+            anti_alias: false,
+            invert: false,
+            ignore_alpha: false,
+            alpha_threshold: 0,
+            grayscale_gamma: 1.0,
+            grayscale_weights: (0.299, 0.587, 0.114),
+            binary_threshold: 128,
+            binary_threshold_otsu: false,
+            dither: false,
+            canny_preprocess: false,
+            canny_low_threshold: 10.0,
+            canny_high_threshold: 30.0,
+            min_path_length: None,
+            path_join_tolerance: 0.5,
+            stroke_mode: StrokeMode::Filled,
+            stroke_width: None,
+            background_color: None,
+            global_opacity: 1.0,
+            blend_mode: None,
+            resize_to: None,
+            region: None,
+            aspect_ratio: None,
+            aspect_ratio_fit: AspectRatioFit::Pad,
+            viewbox_margin: 0,
+            translate: None,
+            clip_to_bounds: false,
+            scale: 1.0,
+            unit: None,
+            preserve_dpi: true,
+            layer_names: false,
+            output_id_prefix: default_output_id_prefix(),
+            embed_original: false,
+            svg_title: None,
+            svg_desc: None,
+            optimize_svg: false,
+            dedupe_paths: false,
+            path_sort: PathSort::Area,
+            close_paths: true,
+            output_indent: None,
+            max_output_bytes: None,
+            output_format: OutputFormat::Svg,
+            output_encoding: OutputEncoding::Utf8,
+            split_by_color: false,
+            parallel: false,
+            cpu_threads: None,
+            random_seed: None,
         }
     }
 }
@@ -70,12 +702,50 @@ impl FromStr for ColorMode {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "color" => Ok(Self::Color),
-            "binary" => Ok(Self::Binary),
+            "binary" | "bw" => Ok(Self::Binary),
+            "grayscale" | "gray" | "grey" => Ok(Self::Grayscale),
             _ => Err(format!("unknown ColorMode {}", s)),
         }
     }
 }
 
+/// The value of `--colormode`
+impl std::fmt::Display for ColorMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Color => "color",
+            Self::Binary => "bw",
+            Self::Grayscale => "gray",
+        })
+    }
+}
+
+impl FromStr for PathSimplifyMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" | "pixel" => Ok(Self::None),
+            "polygon" => Ok(Self::Polygon),
+            "spline" => Ok(Self::Spline),
+            "bezier" => Ok(Self::Bezier),
+            _ => Err(format!("unknown PathSimplifyMode {}", s)),
+        }
+    }
+}
+
+/// The value of `--mode`
+impl std::fmt::Display for PathSimplifyMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::None => "pixel",
+            Self::Polygon => "polygon",
+            Self::Spline => "spline",
+            Self::Bezier => "bezier",
+        })
+    }
+}
+
 impl FromStr for Hierarchical {
     type Err = String;
 
@@ -88,6 +758,292 @@ impl FromStr for Hierarchical {
     }
 }
 
+/// The value of `--hierarchical`
+impl std::fmt::Display for Hierarchical {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Stacked => "stacked",
+            Self::Cutout => "cutout",
+        })
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "svg" => Ok(Self::Svg),
+            "dxf" => Ok(Self::Dxf),
+            "pdf" => Ok(Self::Pdf),
+            "eps" => Ok(Self::Eps),
+            "svgz" | "svg-compressed" | "svg_compressed" => Ok(Self::SvgCompressed),
+            "png-trace" | "png_trace" => Ok(Self::PngTrace),
+            "canvas" => Ok(Self::Canvas),
+            "rust" => Ok(Self::Rust),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("unknown OutputFormat {}", s)),
+        }
+    }
+}
+
+impl OutputFormat {
+    /// File extension of the format, without the leading dot
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Svg => "svg",
+            Self::Dxf => "dxf",
+            Self::Pdf => "pdf",
+            Self::Eps => "eps",
+            Self::SvgCompressed => "svgz",
+            Self::PngTrace => "png",
+            Self::Canvas => "html",
+            Self::Rust => "rs",
+            Self::Json => "json",
+        }
+    }
+}
+
+impl FromStr for Rgba {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = s.strip_prefix('#').unwrap_or(s);
+        if !matches!(digits.len(), 6 | 8) || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(format!("{} is not a RRGGBB or RRGGBBAA hex color", s));
+        }
+        let channel = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).unwrap();
+        Ok(Self {
+            r: channel(0),
+            g: channel(2),
+            b: channel(4),
+            a: if digits.len() == 8 { channel(6) } else { 255 },
+        })
+    }
+}
+
+impl std::fmt::Display for Rgba {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{:02X}{:02X}{:02X}{:02X}",
+            self.r, self.g, self.b, self.a
+        )
+    }
+}
+
+impl TryFrom<String> for Rgba {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Rgba> for String {
+    fn from(color: Rgba) -> Self {
+        color.to_string()
+    }
+}
+
+impl FromStr for BlendMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "normal" => Ok(Self::Normal),
+            "multiply" => Ok(Self::Multiply),
+            "screen" => Ok(Self::Screen),
+            "overlay" => Ok(Self::Overlay),
+            _ => Err(format!("unknown BlendMode {}", s)),
+        }
+    }
+}
+
+/// The CSS keyword
+impl std::fmt::Display for BlendMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Normal => "normal",
+            Self::Multiply => "multiply",
+            Self::Screen => "screen",
+            Self::Overlay => "overlay",
+        })
+    }
+}
+
+impl FromStr for PathSort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "area" => Ok(Self::Area),
+            "color" => Ok(Self::Color),
+            "none" => Ok(Self::None),
+            _ => Err(format!("unknown PathSort {}", s)),
+        }
+    }
+}
+
+impl FromStr for ColorSpace {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rgb" | "RGB" => Ok(Self::Rgb),
+            "lab" | "Lab" | "LAB" => Ok(Self::Lab),
+            _ => Err(format!("unknown ColorSpace {}", s)),
+        }
+    }
+}
+
+impl FromStr for InputColorspace {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "srgb" | "sRGB" => Ok(Self::Srgb),
+            "linear" => Ok(Self::Linear),
+            _ => Err(format!("unknown InputColorspace {}", s)),
+        }
+    }
+}
+
+impl FromStr for OutputEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "utf8" | "utf-8" => Ok(Self::Utf8),
+            "latin1" | "latin-1" | "iso-8859-1" => Ok(Self::Latin1),
+            _ => Err(format!("unknown OutputEncoding {}", s)),
+        }
+    }
+}
+
+impl FromStr for Unit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "px" => Ok(Self::Px),
+            "mm" => Ok(Self::Mm),
+            "cm" => Ok(Self::Cm),
+            "in" => Ok(Self::In),
+            _ => Err(format!("unknown Unit {}", s)),
+        }
+    }
+}
+
+impl Unit {
+    /// Units per inch
+    pub fn per_inch(&self) -> f64 {
+        match self {
+            Self::Px => 96.0,
+            Self::Mm => 25.4,
+            Self::Cm => 2.54,
+            Self::In => 1.0,
+        }
+    }
+
+    /// Suffix of lengths in this unit, as in CSS
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            Self::Px => "px",
+            Self::Mm => "mm",
+            Self::Cm => "cm",
+            Self::In => "in",
+        }
+    }
+}
+
+impl FromStr for AspectRatio {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || format!("{} is not an aspect ratio W:H of positive integers", s);
+        let (width, height) = s.split_once(':').ok_or_else(error)?;
+        match (width.trim().parse(), height.trim().parse()) {
+            (Ok(width), Ok(height)) if width > 0 && height > 0 => Ok(Self { width, height }),
+            _ => Err(error()),
+        }
+    }
+}
+
+impl std::fmt::Display for AspectRatio {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}:{}", self.width, self.height)
+    }
+}
+
+impl TryFrom<String> for AspectRatio {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<AspectRatio> for String {
+    fn from(ratio: AspectRatio) -> Self {
+        ratio.to_string()
+    }
+}
+
+impl FromStr for Rect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || format!("{} is not a rectangle X,Y,W,H of a positive size", s);
+        let numbers: Vec<u32> = s
+            .split(',')
+            .map(|number| number.trim().parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| error())?;
+        match numbers[..] {
+            [x, y, width, height] if width > 0 && height > 0 => Ok(Self {
+                x,
+                y,
+                width,
+                height,
+            }),
+            _ => Err(error()),
+        }
+    }
+}
+
+impl std::fmt::Display for Rect {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{},{},{},{}", self.x, self.y, self.width, self.height)
+    }
+}
+
+impl TryFrom<String> for Rect {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Rect> for String {
+    fn from(rect: Rect) -> Self {
+        rect.to_string()
+    }
+}
+
+impl FromStr for AspectRatioFit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pad" => Ok(Self::Pad),
+            "crop" => Ok(Self::Crop),
+            _ => Err(format!("unknown AspectRatioFit {}", s)),
+        }
+    }
+}
+
 impl FromStr for Preset {
     type Err = String;
 
@@ -96,73 +1052,786 @@ impl FromStr for Preset {
             "bw" => Ok(Self::Bw),
             "poster" => Ok(Self::Poster),
             "photo" => Ok(Self::Photo),
+            "line" => Ok(Self::Line),
+            "auto" => Ok(Self::Auto),
+            "custom" => Err(String::from("the custom Preset must be loaded from a file")),
             _ => Err(format!("unknown Preset {}", s)),
         }
     }
 }
 
+/// The value of `--preset`
+impl std::fmt::Display for Preset {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Bw => "bw",
+            Self::Poster => "poster",
+            Self::Photo => "photo",
+            Self::Line => "line",
+            Self::Custom(_) => "custom",
+            Self::Auto => "auto",
+        })
+    }
+}
+
+/// The tracing settings as command line options, such as
+/// `--colormode color --hierarchical stacked --mode spline --filter_speckle 4 ...`.
+/// Output settings are left out; `Debug` shows every field.
+impl std::fmt::Display for Config {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "--colormode {} --hierarchical {} --mode {} --filter_speckle {}",
+            self.color_mode, self.hierarchical, self.mode, self.filter_speckle
+        )?;
+        if let Some(area) = self.filter_speckle_area_direct {
+            write!(f, " --min-area {}", area)?;
+        }
+        write!(
+            f,
+            " --color_precision {} --gradient_step {} --corner_threshold {} --segment_length {} \
+             --splice_threshold {} --max-iterations {}",
+            self.color_precision,
+            self.layer_difference,
+            self.corner_threshold,
+            self.length_threshold,
+            self.splice_threshold,
+            self.max_iterations
+        )?;
+        if matches!(self.stroke_mode, StrokeMode::Stroked) {
+            write!(f, " --stroke-width {}", self.stroke_width.unwrap_or(1.0))?;
+        }
+        if let Some(precision) = self.path_precision {
+            write!(f, " --path_precision {}", precision)?;
+        }
+        Ok(())
+    }
+}
+
+/// One `name = value` line per field, with the option values the derived fields come from,
+/// such as `filter_speckle_area = 16 (4²)` or `corner_threshold = 1.047 rad (60°)`
+impl std::fmt::Display for ConverterConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fn optional<T: std::fmt::Display>(value: Option<T>) -> String {
+            value.map_or_else(|| String::from("none"), |value| value.to_string())
+        }
+        let angle = |radians: f64| format!("{:.3} rad ({}°)", radians, rad2deg(radians));
+        let side = self.filter_speckle_area.isqrt();
+        let filter_speckle_area = if side * side == self.filter_speckle_area {
+            format!("{} ({}²)", self.filter_speckle_area, side)
+        } else {
+            self.filter_speckle_area.to_string()
+        };
+        let lines = [
+            ("color_mode", self.color_mode.to_string()),
+            ("hierarchical", self.hierarchical.to_string()),
+            ("filter_speckle_area", filter_speckle_area),
+            (
+                "color_precision_loss",
+                format!(
+                    "{} (color_precision {})",
+                    self.color_precision_loss,
+                    8 - self.color_precision_loss
+                ),
+            ),
+            ("layer_difference", self.layer_difference.to_string()),
+            ("color_space", format!("{:?}", self.color_space)),
+            ("input_colorspace", format!("{:?}", self.input_colorspace)),
+            ("mode", self.mode.to_string()),
+            ("corner_threshold", angle(self.corner_threshold)),
+            ("length_threshold", self.length_threshold.to_string()),
+            ("max_iterations", self.max_iterations.to_string()),
+            ("splice_threshold", angle(self.splice_threshold)),
+            ("path_precision", optional(self.path_precision)),
+            ("bezier_tolerance", self.bezier_tolerance.to_string()),
+            ("corner_rounding", self.corner_rounding.to_string()),
+            ("max_colors", optional(self.max_colors)),
+            ("layer_limit", optional(self.layer_limit)),
+            (
+                "max_paths_per_cluster",
+                optional(self.max_paths_per_cluster),
+            ),
+            (
+                "palette_file",
+                optional(self.palette_file.as_ref().map(|path| path.display())),
+            ),
+            ("anti_alias", self.anti_alias.to_string()),
+            ("invert", self.invert.to_string()),
+            ("ignore_alpha", self.ignore_alpha.to_string()),
+            ("alpha_threshold", self.alpha_threshold.to_string()),
+            ("grayscale_gamma", self.grayscale_gamma.to_string()),
+            (
+                "grayscale_weights",
+                format!(
+                    "{}, {}, {}",
+                    self.grayscale_weights.0, self.grayscale_weights.1, self.grayscale_weights.2
+                ),
+            ),
+            (
+                "binary_threshold",
+                self.binary_threshold
+                    .map_or_else(|| String::from("otsu"), |threshold| threshold.to_string()),
+            ),
+            ("dither", self.dither.to_string()),
+            ("canny_preprocess", self.canny_preprocess.to_string()),
+            ("canny_low_threshold", self.canny_low_threshold.to_string()),
+            (
+                "canny_high_threshold",
+                self.canny_high_threshold.to_string(),
+            ),
+            ("min_path_length", optional(self.min_path_length)),
+            ("path_join_tolerance", self.path_join_tolerance.to_string()),
+            (
+                "stroke_width",
+                self.stroke_width
+                    .map_or_else(|| String::from("none (filled)"), |width| width.to_string()),
+            ),
+            ("background_color", optional(self.background_color)),
+            ("global_opacity", self.global_opacity.to_string()),
+            ("blend_mode", optional(self.blend_mode)),
+            (
+                "resize_to",
+                optional(
+                    self.resize_to
+                        .map(|(width, height)| format!("{}x{}", width, height)),
+                ),
+            ),
+            ("region", optional(self.region)),
+            ("aspect_ratio", optional(self.aspect_ratio)),
+            ("aspect_ratio_fit", format!("{:?}", self.aspect_ratio_fit)),
+            ("viewbox_margin", self.viewbox_margin.to_string()),
+            (
+                "translate",
+                optional(self.translate.map(|(x, y)| format!("{}, {}", x, y))),
+            ),
+            ("clip_to_bounds", self.clip_to_bounds.to_string()),
+            ("scale", self.scale.to_string()),
+            (
+                "unit",
+                optional(self.unit.map(|unit| format!("{:?}", unit))),
+            ),
+            ("layer_names", self.layer_names.to_string()),
+            ("output_id_prefix", self.output_id_prefix.clone()),
+            ("embed_original", self.embed_original.to_string()),
+            ("svg_title", optional(self.svg_title.as_ref())),
+            ("svg_desc", optional(self.svg_desc.as_ref())),
+            ("optimize_svg", self.optimize_svg.to_string()),
+            ("dedupe_paths", self.dedupe_paths.to_string()),
+            ("path_sort", format!("{:?}", self.path_sort)),
+            ("close_paths", self.close_paths.to_string()),
+            ("output_indent", optional(self.output_indent)),
+            ("max_output_bytes", optional(self.max_output_bytes)),
+            ("output_encoding", format!("{:?}", self.output_encoding)),
+            ("parallel", self.parallel.to_string()),
+            ("cpu_threads", optional(self.cpu_threads)),
+            ("random_seed", optional(self.random_seed)),
+        ];
+        for (i, (name, value)) in lines.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{:<22} = {}", name, value)?;
+        }
+        Ok(())
+    }
+}
+
 impl Config {
     pub fn from_preset(preset: Preset) -> Self {
         match preset {
             Preset::Bw => Self {
                 color_mode: ColorMode::Binary,
-                hierarchical: Hierarchical::Stacked,
-                filter_speckle: 4,
-                color_precision: 6,
-                layer_difference: 16,
-                mode: PathSimplifyMode::Spline,
-                corner_threshold: 60,
-                length_threshold: 4.0,
-                max_iterations: 10,
-                splice_threshold: 45,
-                path_precision: Some(2),
+                ..Self::default()
+            },
+            Preset::Line => Self {
+                color_mode: ColorMode::Binary,
+                filter_speckle: 2,
+                mode: PathSimplifyMode::Polygon,
+                corner_threshold: 30,
+                stroke_mode: StrokeMode::Stroked,
+                stroke_width: Some(1.0),
+                ..Self::default()
             },
             Preset::Poster => Self {
-                color_mode: ColorMode::Color,
-                hierarchical: Hierarchical::Stacked,
-                filter_speckle: 4,
                 color_precision: 8,
-                layer_difference: 16,
-                mode: PathSimplifyMode::Spline,
-                corner_threshold: 60,
-                length_threshold: 4.0,
-                max_iterations: 10,
-                splice_threshold: 45,
-                path_precision: Some(2),
+                ..Self::default()
             },
             Preset::Photo => Self {
-                color_mode: ColorMode::Color,
-                hierarchical: Hierarchical::Stacked,
                 filter_speckle: 10,
                 color_precision: 8,
                 layer_difference: 48,
-                mode: PathSimplifyMode::Spline,
                 corner_threshold: 180,
-                length_threshold: 4.0,
-                max_iterations: 10,
-                splice_threshold: 45,
-                path_precision: Some(2),
+                ..Self::default()
             },
+            Preset::Custom(config) => *config,
+            Preset::Auto => Self::default(),
         }
     }
 
-    pub(crate) fn into_converter_config(self) -> ConverterConfig {
-        ConverterConfig {
-            color_mode: self.color_mode,
-            hierarchical: self.hierarchical,
-            filter_speckle_area: self.filter_speckle * self.filter_speckle,
-            color_precision_loss: 8 - self.color_precision,
-            layer_difference: self.layer_difference,
-            mode: self.mode,
-            corner_threshold: deg2rad(self.corner_threshold),
-            length_threshold: self.length_threshold,
-            max_iterations: self.max_iterations,
-            splice_threshold: deg2rad(self.splice_threshold),
-            path_precision: self.path_precision,
+    /// Build a config from the defaults, with range checks, e.g.
+    /// `Config::builder().color_mode(ColorMode::Binary).filter_speckle(6).build()`
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::new()
+    }
+
+    /// Save the config as a TOML file
+    #[cfg(feature = "serde")]
+    pub fn save_toml(&self, path: impl AsRef<Path>) -> Result<(), VTracerError> {
+        let string =
+            toml::to_string(self).map_err(|err| VTracerError::ConfigFile(err.to_string()))?;
+        std::fs::write(path, string)
+            .map_err(|_| VTracerError::ConfigFile(String::from("Cannot write config file.")))
+    }
+
+    #[cfg(not(feature = "serde"))]
+    pub fn save_toml(&self, _path: impl AsRef<Path>) -> Result<(), VTracerError> {
+        Err(serde_required())
+    }
+
+    /// Load a config previously saved by `save_toml`
+    #[cfg(feature = "serde")]
+    pub fn load_toml(path: impl AsRef<Path>) -> Result<Self, VTracerError> {
+        let string = std::fs::read_to_string(path).map_err(|_| {
+            VTracerError::ConfigFile(String::from("No config file found at specified path"))
+        })?;
+        toml::from_str(&string).map_err(|err| VTracerError::ConfigFile(err.to_string()))
+    }
+
+    #[cfg(not(feature = "serde"))]
+    pub fn load_toml(_path: impl AsRef<Path>) -> Result<Self, VTracerError> {
+        Err(serde_required())
+    }
+
+    /// Parse a config from JSON, in the same shape as the TOML written by `save_toml`
+    #[cfg(feature = "serde")]
+    pub fn from_json_str(s: &str) -> Result<Self, VTracerError> {
+        serde_json::from_str(s).map_err(|err| VTracerError::ConfigFile(err.to_string()))
+    }
+
+    #[cfg(not(feature = "serde"))]
+    pub fn from_json_str(_s: &str) -> Result<Self, VTracerError> {
+        Err(serde_required())
+    }
+
+    /// `base` with every field of `overrides` that differs from `Config::default()`,
+    /// e.g. a preset with the options given on top of it. A field cannot be overridden
+    /// back to its default value this way.
+    pub fn merge(mut base: Config, overrides: Config) -> Config {
+        macro_rules! merge_fields {
+            ($defaults:ident; $($field:ident,)*) => {
+                $(
+                    if overrides.$field != $defaults.$field {
+                        base.$field = overrides.$field;
+                    }
+                )*
+            };
         }
+        let defaults = Config::default();
+        config_fields!(merge_fields!(defaults));
+        base
     }
+
+    /// The fields that differ from `Config::default()`, sorted by name, with their values,
+    /// e.g. `("mode", "polygon")`. Unset optional fields are `none`.
+    pub fn diff_from_default(&self) -> Vec<(FieldName, String)> {
+        macro_rules! diff_fields {
+            ($defaults:ident, $fields:ident; $($field:ident,)*) => {
+                $(
+                    if self.$field != $defaults.$field {
+                        $fields.push((String::from(stringify!($field)), self.$field.field_value()));
+                    }
+                )*
+            };
+        }
+        let defaults = Config::default();
+        let mut fields = vec![];
+        config_fields!(diff_fields!(defaults, fields));
+        fields.sort();
+        fields
+    }
+}
+
+/// How `Config::diff_from_default` writes a field
+trait FieldValue {
+    fn field_value(&self) -> String;
+}
+
+macro_rules! display_field_value {
+    ($($type:ty),*) => {
+        $(
+            impl FieldValue for $type {
+                fn field_value(&self) -> String {
+                    self.to_string()
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! debug_field_value {
+    ($($type:ty),*) => {
+        $(
+            impl FieldValue for $type {
+                fn field_value(&self) -> String {
+                    format!("{:?}", self)
+                }
+            }
+        )*
+    };
+}
+
+display_field_value!(
+    bool,
+    u8,
+    u32,
+    u64,
+    usize,
+    i32,
+    f64,
+    String,
+    ColorMode,
+    Hierarchical,
+    PathSimplifyMode,
+    Rgba,
+    BlendMode,
+    AspectRatio,
+    Rect
+);
+debug_field_value!(
+    ColorSpace,
+    InputColorspace,
+    AspectRatioFit,
+    OutputEncoding,
+    OutputFormat,
+    PathSort,
+    StrokeMode,
+    Unit
+);
+
+impl FieldValue for PathBuf {
+    fn field_value(&self) -> String {
+        self.display().to_string()
+    }
+}
+
+impl<T: FieldValue> FieldValue for Option<T> {
+    fn field_value(&self) -> String {
+        self.as_ref()
+            .map_or_else(|| String::from("none"), FieldValue::field_value)
+    }
+}
+
+impl<A: FieldValue, B: FieldValue> FieldValue for (A, B) {
+    fn field_value(&self) -> String {
+        format!("{}, {}", self.0.field_value(), self.1.field_value())
+    }
+}
+
+impl<A: FieldValue, B: FieldValue, C: FieldValue> FieldValue for (A, B, C) {
+    fn field_value(&self) -> String {
+        format!(
+            "{}, {}, {}",
+            self.0.field_value(),
+            self.1.field_value(),
+            self.2.field_value()
+        )
+    }
+}
+
+/// Degrees are converted to radians, and `color_precision` to the bits dropped
+impl From<Config> for ConverterConfig {
+    fn from(config: Config) -> Self {
+        Self {
+            color_mode: config.color_mode,
+            hierarchical: config.hierarchical,
+            filter_speckle_area: match config.filter_speckle_area_direct {
+                Some(area) => area.max(0.0).ceil() as usize,
+                None => config.filter_speckle * config.filter_speckle,
+            },
+            color_precision_loss: 8 - config.color_precision,
+            layer_difference: config.layer_difference,
+            color_space: config.color_space,
+            input_colorspace: config.input_colorspace,
+            mode: config.mode,
+            corner_threshold: deg2rad(config.corner_threshold),
+            length_threshold: config.length_threshold,
+            max_iterations: config.max_iterations,
+            splice_threshold: deg2rad(config.splice_threshold),
+            path_precision: config.path_precision,
+            bezier_tolerance: config.bezier_tolerance,
+            corner_rounding: config.corner_rounding,
+            max_colors: config.max_colors,
+            layer_limit: config.layer_limit,
+            max_paths_per_cluster: config.max_paths_per_cluster,
+            palette_file: config.palette_file,
+            anti_alias: config.anti_alias,
+            invert: config.invert,
+            ignore_alpha: config.ignore_alpha,
+            alpha_threshold: config.alpha_threshold,
+            grayscale_gamma: config.grayscale_gamma,
+            grayscale_weights: config.grayscale_weights,
+            binary_threshold: if config.binary_threshold_otsu {
+                None
+            } else {
+                Some(config.binary_threshold)
+            },
+            dither: config.dither,
+            canny_preprocess: config.canny_preprocess,
+            canny_low_threshold: config.canny_low_threshold,
+            canny_high_threshold: config.canny_high_threshold,
+            min_path_length: config.min_path_length,
+            path_join_tolerance: config.path_join_tolerance,
+            stroke_width: match config.stroke_mode {
+                StrokeMode::Filled => None,
+                StrokeMode::Stroked => Some(config.stroke_width.unwrap_or(1.0)),
+            },
+            background_color: config.background_color,
+            global_opacity: config.global_opacity,
+            blend_mode: config.blend_mode,
+            resize_to: config.resize_to,
+            region: config.region,
+            aspect_ratio: config.aspect_ratio,
+            aspect_ratio_fit: config.aspect_ratio_fit,
+            viewbox_margin: config.viewbox_margin,
+            translate: config.translate,
+            clip_to_bounds: config.clip_to_bounds,
+            scale: config.scale,
+            unit: config.unit,
+            layer_names: config.layer_names,
+            output_id_prefix: config.output_id_prefix,
+            // The trace is drawn over the original image
+            embed_original: config.embed_original
+                || matches!(config.output_format, OutputFormat::PngTrace),
+            svg_title: config.svg_title,
+            svg_desc: config.svg_desc,
+            optimize_svg: config.optimize_svg,
+            dedupe_paths: config.dedupe_paths,
+            path_sort: config.path_sort,
+            close_paths: config.close_paths,
+            output_indent: config.output_indent,
+            max_output_bytes: config.max_output_bytes,
+            output_encoding: config.output_encoding,
+            parallel: config.parallel,
+            cpu_threads: config.cpu_threads,
+            random_seed: config.random_seed,
+        }
+    }
+}
+
+/// Reverses `From<Config> for ConverterConfig`, for inspecting or serializing a converter config.
+/// Angles are rounded to whole degrees. A speckle area that is not a square is kept as
+/// `filter_speckle_area_direct`. Settings the converter config does not carry are left at
+/// their defaults.
+impl From<ConverterConfig> for Config {
+    fn from(config: ConverterConfig) -> Self {
+        let filter_speckle = config.filter_speckle_area.isqrt();
+        Self {
+            color_mode: config.color_mode,
+            hierarchical: config.hierarchical,
+            filter_speckle,
+            filter_speckle_area_direct: if filter_speckle * filter_speckle
+                == config.filter_speckle_area
+            {
+                None
+            } else {
+                Some(config.filter_speckle_area as f64)
+            },
+            color_precision: 8 - config.color_precision_loss,
+            layer_difference: config.layer_difference,
+            color_space: config.color_space,
+            input_colorspace: config.input_colorspace,
+            mode: config.mode,
+            corner_threshold: rad2deg(config.corner_threshold),
+            length_threshold: config.length_threshold,
+            max_iterations: config.max_iterations,
+            splice_threshold: rad2deg(config.splice_threshold),
+            path_precision: config.path_precision,
+            bezier_tolerance: config.bezier_tolerance,
+            corner_rounding: config.corner_rounding,
+            max_colors: config.max_colors,
+            layer_limit: config.layer_limit,
+            max_paths_per_cluster: config.max_paths_per_cluster,
+            palette_file: config.palette_file,
+            anti_alias: config.anti_alias,
+            invert: config.invert,
+            ignore_alpha: config.ignore_alpha,
+            alpha_threshold: config.alpha_threshold,
+            grayscale_gamma: config.grayscale_gamma,
+            grayscale_weights: config.grayscale_weights,
+            binary_threshold: config.binary_threshold.unwrap_or(128),
+            binary_threshold_otsu: config.binary_threshold.is_none(),
+            dither: config.dither,
+            canny_preprocess: config.canny_preprocess,
+            canny_low_threshold: config.canny_low_threshold,
+            canny_high_threshold: config.canny_high_threshold,
+            min_path_length: config.min_path_length,
+            path_join_tolerance: config.path_join_tolerance,
+            stroke_mode: match config.stroke_width {
+                Some(_) => StrokeMode::Stroked,
+                None => StrokeMode::Filled,
+            },
+            stroke_width: config.stroke_width,
+            background_color: config.background_color,
+            global_opacity: config.global_opacity,
+            blend_mode: config.blend_mode,
+            resize_to: config.resize_to,
+            region: config.region,
+            aspect_ratio: config.aspect_ratio,
+            aspect_ratio_fit: config.aspect_ratio_fit,
+            viewbox_margin: config.viewbox_margin,
+            translate: config.translate,
+            clip_to_bounds: config.clip_to_bounds,
+            scale: config.scale,
+            unit: config.unit,
+            layer_names: config.layer_names,
+            output_id_prefix: config.output_id_prefix,
+            embed_original: config.embed_original,
+            svg_title: config.svg_title,
+            svg_desc: config.svg_desc,
+            optimize_svg: config.optimize_svg,
+            dedupe_paths: config.dedupe_paths,
+            path_sort: config.path_sort,
+            close_paths: config.close_paths,
+            output_indent: config.output_indent,
+            max_output_bytes: config.max_output_bytes,
+            output_encoding: config.output_encoding,
+            parallel: config.parallel,
+            cpu_threads: config.cpu_threads,
+            random_seed: config.random_seed,
+            ..Config::default()
+        }
+    }
+}
+
+/// Whether `prefix` can start the ids of `Config::output_id_prefix`: letters, digits, `-`
+/// and `_` only, as ids are referenced in `url(#...)`, which takes no escapes
+pub fn is_valid_id_prefix(prefix: &str) -> bool {
+    prefix
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+#[cfg(not(feature = "serde"))]
+fn serde_required() -> VTracerError {
+    VTracerError::ConfigFile(String::from("Config files require the `serde` feature"))
+}
+
+#[cfg(feature = "serde")]
+fn default_bezier_tolerance() -> f64 {
+    0.5
+}
+
+#[cfg(feature = "serde")]
+fn default_grayscale_gamma() -> f64 {
+    1.0
+}
+
+#[cfg(feature = "serde")]
+fn default_grayscale_weights() -> (f64, f64, f64) {
+    (0.299, 0.587, 0.114)
+}
+
+#[cfg(feature = "serde")]
+fn default_binary_threshold() -> u8 {
+    128
+}
+
+#[cfg(feature = "serde")]
+fn default_canny_low_threshold() -> f64 {
+    10.0
+}
+
+#[cfg(feature = "serde")]
+fn default_canny_high_threshold() -> f64 {
+    30.0
+}
+
+#[cfg(feature = "serde")]
+fn default_scale() -> f64 {
+    1.0
+}
+
+#[cfg(feature = "serde")]
+fn default_path_join_tolerance() -> f64 {
+    0.5
+}
+
+#[cfg(feature = "serde")]
+fn default_close_paths() -> bool {
+    true
+}
+
+#[cfg(feature = "serde")]
+fn default_preserve_dpi() -> bool {
+    true
+}
+
+fn default_output_id_prefix() -> String {
+    String::from("vt")
+}
+
+#[cfg(feature = "serde")]
+fn default_global_opacity() -> f64 {
+    1.0
 }
 
 fn deg2rad(deg: i32) -> f64 {
     deg as f64 / 180.0 * std::f64::consts::PI
 }
+
+fn rad2deg(rad: f64) -> i32 {
+    (rad / std::f64::consts::PI * 180.0).round() as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "serde")]
+    use serde::de::DeserializeOwned;
+    #[cfg(feature = "serde")]
+    use std::fmt::Debug;
+
+    /// Check that `value` is written as the string `name` and read back unchanged
+    #[cfg(feature = "serde")]
+    fn roundtrip<T: Serialize + DeserializeOwned + PartialEq + Debug>(value: T, name: &str) {
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(json, format!("\"{}\"", name));
+        assert_eq!(serde_json::from_str::<T>(&json).unwrap(), value);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn enum_variants_roundtrip() {
+        roundtrip(ColorMode::Color, "color");
+        roundtrip(ColorMode::Binary, "binary");
+        roundtrip(ColorMode::Grayscale, "grayscale");
+        roundtrip(Hierarchical::Stacked, "stacked");
+        roundtrip(Hierarchical::Cutout, "cutout");
+        roundtrip(PathSimplifyMode::None, "none");
+        roundtrip(PathSimplifyMode::Polygon, "polygon");
+        roundtrip(PathSimplifyMode::Spline, "spline");
+        roundtrip(PathSimplifyMode::Bezier, "bezier");
+        roundtrip(OutputFormat::Svg, "svg");
+        roundtrip(OutputFormat::Dxf, "dxf");
+        roundtrip(OutputFormat::Pdf, "pdf");
+        roundtrip(OutputFormat::Eps, "eps");
+        roundtrip(OutputFormat::SvgCompressed, "svg_compressed");
+        roundtrip(OutputFormat::PngTrace, "png_trace");
+        roundtrip(OutputFormat::Canvas, "canvas");
+        roundtrip(OutputFormat::Rust, "rust");
+        roundtrip(OutputFormat::Json, "json");
+        roundtrip(StrokeMode::Filled, "filled");
+        roundtrip(StrokeMode::Stroked, "stroked");
+        roundtrip(Unit::Px, "px");
+        roundtrip(Unit::Mm, "mm");
+        roundtrip(Unit::Cm, "cm");
+        roundtrip(Unit::In, "in");
+        roundtrip(AspectRatioFit::Pad, "pad");
+        roundtrip(AspectRatioFit::Crop, "crop");
+        roundtrip(PathSort::Area, "area");
+        roundtrip(PathSort::Color, "color");
+        roundtrip(PathSort::None, "none");
+        roundtrip(BlendMode::Normal, "normal");
+        roundtrip(BlendMode::Multiply, "multiply");
+        roundtrip(BlendMode::Screen, "screen");
+        roundtrip(BlendMode::Overlay, "overlay");
+        roundtrip(ColorSpace::Rgb, "rgb");
+        roundtrip(ColorSpace::Lab, "lab");
+        roundtrip(InputColorspace::Srgb, "srgb");
+        roundtrip(InputColorspace::Linear, "linear");
+        roundtrip(OutputEncoding::Utf8, "utf8");
+        roundtrip(OutputEncoding::Latin1, "latin1");
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn configs_roundtrip() {
+        let custom = Config {
+            max_colors: Some(12),
+            background_color: Some(Rgba {
+                r: 1,
+                g: 2,
+                b: 3,
+                a: 4,
+            }),
+            region: Some(Rect {
+                x: 1,
+                y: 2,
+                width: 3,
+                height: 4,
+            }),
+            aspect_ratio: Some(AspectRatio {
+                width: 16,
+                height: 9,
+            }),
+            blend_mode: Some(BlendMode::Multiply),
+            unit: Some(Unit::Mm),
+            resize_to: Some((64, 48)),
+            svg_title: Some(String::from("Title")),
+            ..Config::default()
+        };
+        let presets = [Preset::Bw, Preset::Poster, Preset::Photo, Preset::Line];
+        let configs = presets
+            .into_iter()
+            .map(Config::from_preset)
+            .chain([Config::default(), custom]);
+        for config in configs {
+            let json = serde_json::to_string(&config).unwrap();
+            assert_eq!(Config::from_json_str(&json).unwrap(), config);
+            let toml = toml::to_string(&config).unwrap();
+            assert_eq!(toml::from_str::<Config>(&toml).unwrap(), config);
+        }
+    }
+
+    #[test]
+    fn color_modes_parse_their_display() {
+        for mode in [ColorMode::Color, ColorMode::Binary, ColorMode::Grayscale] {
+            assert_eq!(mode.to_string().parse::<ColorMode>(), Ok(mode));
+        }
+    }
+
+    #[test]
+    fn diff_lists_the_changed_fields() {
+        assert!(Config::default().diff_from_default().is_empty());
+        let config = Config {
+            mode: PathSimplifyMode::Polygon,
+            color_space: ColorSpace::Lab,
+            max_colors: Some(8),
+            grayscale_weights: (0.25, 0.5, 0.25),
+            svg_title: Some(String::from("Title")),
+            ..Config::default()
+        };
+        let diff = config.diff_from_default();
+        let fields: Vec<(&str, &str)> = diff
+            .iter()
+            .map(|(field, value)| (field.as_str(), value.as_str()))
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                ("color_space", "Lab"),
+                ("grayscale_weights", "0.25, 0.5, 0.25"),
+                ("max_colors", "8"),
+                ("mode", "polygon"),
+                ("svg_title", "Title"),
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_takes_every_changed_field() {
+        let overrides = Config {
+            color_precision: 3,
+            global_opacity: f64::NAN,
+            svg_title: Some(String::from("Title")),
+            ..Config::default()
+        };
+        let merged = Config::merge(Config::from_preset(Preset::Photo), overrides);
+        assert_eq!(merged.color_precision, 3);
+        assert!(merged.global_opacity.is_nan());
+        assert_eq!(merged.svg_title.as_deref(), Some("Title"));
+        // Fields left at their default keep the value of the base
+        assert_eq!(merged.filter_speckle, 10);
+        assert_eq!(merged.layer_difference, 48);
+    }
+}
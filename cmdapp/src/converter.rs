@@ -1,12 +1,47 @@
-use std::path::Path;
-use std::{fs::File, io::Write};
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs::File;
+use std::hash::Hash;
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
 
-use super::config::{ColorMode, Config, ConverterConfig, Hierarchical};
-use super::svg::SvgFile;
+use super::bezier::fit_compound_path;
+use super::canny::canny_edges;
+use super::canvas::CanvasFile;
+use super::config::{
+    is_valid_id_prefix, AspectRatio, AspectRatioFit, ColorMode, ColorSpace, Config,
+    ConverterConfig, Hierarchical, InputColorspace, OutputFormat, PathSimplifyMode, PathSort, Rect,
+    Rgba, Unit,
+};
+use super::decoder::{decode_image, png_dpi};
+use super::dxf::DxfFile;
+use super::eps::EpsFile;
+use super::error::VTracerError;
+use super::json::JsonFile;
+use super::lab::{lab_to_color, linear_to_srgb, to_lab};
+use super::palette::{hue, load_palette, nearest_color, reduce_colors};
+use super::pdf::PdfFile;
+use super::progress::{NoProgress, ProgressReporter, Stage};
+use super::rust::RustFile;
+use super::stats::{area, ColorStats};
+use super::svg::{EmbeddedImage, SvgFile};
+use super::trace::TracePng;
+use super::vector::VectorPath;
 use fastrand::Rng;
-use visioncortex::color_clusters::{KeyingAction, Runner, RunnerConfig, HIERARCHICAL_MAX};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use image::codecs::png::PngEncoder;
+use image::imageops::{self, FilterType};
+use image::{ColorType, DynamicImage, RgbaImage};
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
+use visioncortex::color_clusters::{
+    Cluster, Clusters, ClustersView, KeyingAction, Runner, RunnerConfig, HIERARCHICAL_MAX,
+};
 use visioncortex::{
-    approximate_circle_with_spline, Color, ColorImage, ColorName, CompoundPath, PathSimplifyMode,
+    approximate_circle_with_spline, BinaryImage, Color, ColorImage, ColorName, CompoundPath,
+    CompoundPathElement, PointF64,
 };
 
 const NUM_UNUSED_COLOR_ITERATIONS: usize = 6;
@@ -15,25 +50,397 @@ const NUM_UNUSED_COLOR_ITERATIONS: usize = 6;
 const KEYING_THRESHOLD: f32 = 0.2;
 
 const SMALL_CIRCLE: i32 = 12;
+/// Maximum distance in RGBA space between a pixel and the blend of two colors for the pixel to
+/// be considered part of an anti-aliased fringe between them
+const FRINGE_TOLERANCE: f64 = 12.0;
+
+/// Convert an in-memory image into an in-memory SVG.
+/// `config` is either a `Config` or a `ConverterConfig`.
+pub fn convert(img: ColorImage, config: impl Into<ConverterConfig>) -> Result<SvgFile, String> {
+    convert_with_progress(img, config, None)
+}
+
+/// Convert an in-memory image into an in-memory SVG, reporting progress to `reporter`
+pub fn convert_with_progress(
+    img: ColorImage,
+    config: impl Into<ConverterConfig>,
+    reporter: Option<Box<dyn ProgressReporter>>,
+) -> Result<SvgFile, String> {
+    convert_reporting_to(img, config, reporter.as_deref().unwrap_or(&NoProgress))
+}
 
-/// Convert an in-memory image into an in-memory SVG
-pub fn convert(img: ColorImage, config: Config) -> Result<SvgFile, String> {
-    let config = config.into_converter_config();
-    match config.color_mode {
-        ColorMode::Color => color_image_to_svg(img, config),
-        ColorMode::Binary => binary_image_to_svg(img, config),
+/// Convert, then convert again with `filter_speckle` raised by 1 while the SVG is larger than
+/// `config.max_output_bytes`
+fn convert_reporting_to(
+    img: ColorImage,
+    config: impl Into<ConverterConfig>,
+    reporter: &dyn ProgressReporter,
+) -> Result<SvgFile, String> {
+    let mut config: ConverterConfig = config.into();
+    let Some(max_bytes) = config.max_output_bytes else {
+        return convert_once(img, config, reporter);
+    };
+    // Past the longest side of the image, every patch is filtered already
+    let longest_side = img.width.max(img.height);
+    let mut retries = 0;
+    loop {
+        let mut svg = convert_once(img.clone(), config.clone(), reporter)?;
+        let size = svg.to_bytes().len();
+        if size <= max_bytes {
+            if retries > 0 {
+                log::info!(
+                    "Simplified {} time(s), to filter_speckle {}, to fit in {} bytes",
+                    retries,
+                    config.filter_speckle_area.isqrt(),
+                    max_bytes
+                );
+            }
+            svg.simplify_iterations = retries;
+            return Ok(svg);
+        }
+        let side = config.filter_speckle_area.isqrt();
+        if side >= longest_side {
+            return Err(format!(
+                "The SVG takes {} bytes with every patch filtered, more than the limit of {}.",
+                size, max_bytes
+            ));
+        }
+        log::debug!(
+            "The SVG takes {} bytes, more than {}, at filter_speckle {}",
+            size,
+            max_bytes,
+            side
+        );
+        config.filter_speckle_area = (side + 1) * (side + 1);
+        retries += 1;
+    }
+}
+
+fn convert_once(
+    img: ColorImage,
+    config: ConverterConfig,
+    reporter: &dyn ProgressReporter,
+) -> Result<SvgFile, String> {
+    log::debug!("Converting a {}x{} image", img.width, img.height);
+    if config.stroke_width.is_some() && !matches!(config.color_mode, ColorMode::Binary) {
+        return Err(String::from(
+            "Stroked paths are only supported in binary color mode.",
+        ));
+    }
+    if config.canny_preprocess && !matches!(config.color_mode, ColorMode::Binary) {
+        return Err(String::from(
+            "Canny edge detection is only supported in binary color mode.",
+        ));
+    }
+    if config.layer_limit == Some(0) {
+        return Err(String::from("The layer limit must be at least 1."));
+    }
+    if !is_valid_id_prefix(&config.output_id_prefix) {
+        return Err(String::from(
+            "The id prefix may only hold letters, digits, '-' and '_'.",
+        ));
+    }
+    let img = match config.resize_to {
+        Some((width, height)) => fit_within(img, width, height)?,
+        None => img,
+    };
+    let img = match config.region {
+        Some(region) => crop(&img, region)?,
+        None => img,
+    };
+    let img = match config.input_colorspace {
+        InputColorspace::Srgb => img,
+        InputColorspace::Linear => linear_to_srgb(img),
+    };
+    let original = if config.embed_original {
+        Some(encode_png(&img)?)
+    } else {
+        None
+    };
+    let img = match config.aspect_ratio {
+        Some(ratio) => {
+            // Transparent white is keyed out in color mode and left blank in binary mode
+            let fill = config
+                .background_color
+                .map_or(Color::new_rgba(255, 255, 255, 0), |color| {
+                    Color::new_rgba(color.r, color.g, color.b, color.a)
+                });
+            fit_aspect_ratio(img, ratio, config.aspect_ratio_fit, fill)
+        }
+        None => img,
+    };
+    let img = if config.alpha_threshold > 0 {
+        clear_below_alpha(img, config.alpha_threshold)
+    } else {
+        img
+    };
+    // After fitting the aspect ratio, so the padding turns white too
+    let img = if config.ignore_alpha {
+        composite_over_white(img)
+    } else {
+        img
+    };
+    let img = if config.anti_alias {
+        remove_fringes(img)
+    } else {
+        img
+    };
+    let img = if config.invert { invert(img) } else { img };
+    let mut svg = match config.color_mode {
+        ColorMode::Color => color_image_to_svg(img, &config, reporter),
+        ColorMode::Binary => {
+            // Grey pixels are their own luminance, so thresholding uses the weights
+            let img = to_grayscale(img, config.grayscale_weights);
+            let img = apply_gamma(img, config.grayscale_gamma);
+            binary_image_to_svg(img, &config, reporter)
+        }
+        ColorMode::Grayscale => {
+            let img = to_grayscale(img, config.grayscale_weights);
+            let img = apply_gamma(img, config.grayscale_gamma);
+            color_image_to_svg(img, &config, reporter)
+        }
+    }?;
+    if let Some(path) = &config.palette_file {
+        let palette = load_palette(path)?;
+        log::debug!("Snapping to {} palette colors", palette.len());
+        for path in svg.paths.iter_mut() {
+            path.color = nearest_color(path.color, &palette);
+        }
+    }
+    svg.join_open_paths(config.path_join_tolerance);
+    if let Some(min_path_length) = config.min_path_length {
+        svg.paths
+            .retain(|path| compound_path_length(&path.path) >= min_path_length);
+    }
+    // Stacked layers are painted over the layers below, so only paths of one color trade
+    // places there. Other paths do not overlap.
+    let stacked = !matches!(config.color_mode, ColorMode::Binary)
+        && matches!(config.hierarchical, Hierarchical::Stacked);
+    let layers = svg
+        .paths
+        .chunk_by_mut(|a, b| !stacked || a.color == b.color);
+    match config.path_sort {
+        PathSort::Area => {
+            // Stable, so paths of equal area stay in stacking order
+            for layer in layers {
+                layer.sort_by_cached_key(|path| Reverse(area(&path.path).round() as u64));
+            }
+        }
+        PathSort::Color => {
+            for layer in layers {
+                layer.sort_by(|a, b| hue(a.color).total_cmp(&hue(b.color)));
+            }
+        }
+        PathSort::None => {}
+    }
+    svg.background_color = config
+        .background_color
+        .map(|color| Color::new_rgba(color.r, color.g, color.b, color.a));
+    svg.opacity = config.global_opacity;
+    svg.blend_mode = config.blend_mode;
+    svg.viewbox_margin = config.viewbox_margin;
+    svg.translate = config.translate;
+    svg.clip_to_bounds = config.clip_to_bounds;
+    svg.scale = config.scale;
+    svg.unit = config.unit;
+    svg.layer_names = config.layer_names;
+    svg.id_prefix = config.output_id_prefix;
+    svg.dedupe_paths = config.dedupe_paths;
+    if matches!(config.mode, PathSimplifyMode::Polygon) {
+        svg.corner_rounding = config.corner_rounding;
+    }
+    svg.encoding = config.output_encoding;
+    svg.indent = config.output_indent;
+    svg.original = original;
+    svg.title = config.svg_title;
+    svg.description = config.svg_desc;
+    if config.optimize_svg {
+        svg.optimize();
     }
+    reporter.on_progress(Stage::PathSimplification, 1.0);
+    log::debug!("Traced {} paths", svg.paths.len());
+    Ok(svg)
 }
 
-/// Convert an image file into svg file
+/// Convert an image file into svg file, or the file format given by `config.output_format`.
+/// With `config.split_by_color`, one file is written per color, next to `output_path`.
 pub fn convert_image_to_svg(
     input_path: &Path,
     output_path: &Path,
     config: Config,
 ) -> Result<(), String> {
-    let img = read_image(input_path)?;
-    let svg = convert(img, config)?;
-    write_svg(svg, output_path)
+    convert_image_to_svg_with_progress(input_path, output_path, config, None)
+}
+
+/// `convert_image_to_svg`, reporting progress to `reporter`
+pub fn convert_image_to_svg_with_progress(
+    input_path: &Path,
+    output_path: &Path,
+    config: Config,
+    reporter: Option<Box<dyn ProgressReporter>>,
+) -> Result<(), String> {
+    let reporter = reporter.as_deref().unwrap_or(&NoProgress);
+    let format = config.output_format;
+    let preserve_dpi = config.preserve_dpi;
+    let split_by_color = config.split_by_color;
+    let (img, dpi) = read_image(input_path)?;
+    reporter.on_progress(Stage::Decoding, 1.0);
+    let mut svg = convert_reporting_to(img, config, reporter)?;
+    if let Some(dpi) = dpi.filter(|_| preserve_dpi) {
+        set_dpi(&mut svg, dpi);
+    }
+    let title = input_path.file_name().map(|name| name.to_string_lossy());
+    if !split_by_color {
+        write_svg(svg, output_path, format, title.as_deref())?;
+    } else {
+        for (color, layer) in svg.split_by_color() {
+            let layer_path = color_layer_path(output_path, &color, format);
+            log::debug!("Writing {}", layer_path.display());
+            write_svg(layer, &layer_path, format, title.as_deref())?;
+        }
+    }
+    reporter.on_progress(Stage::Serialization, 1.0);
+    Ok(())
+}
+
+/// `output_path` with `_color_RRGGBB` appended to the file stem, and the extension of `format`
+fn color_layer_path(output_path: &Path, color: &Color, format: OutputFormat) -> PathBuf {
+    let stem = output_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    output_path.with_file_name(format!(
+        "{}_color_{:02X}{:02X}{:02X}.{}",
+        stem,
+        color.r,
+        color.g,
+        color.b,
+        format.extension()
+    ))
+}
+
+/// Convert an encoded image read from `reader` into svg written to `writer`,
+/// or the file format given by `config.output_format`
+pub fn convert_image_stream_to_svg(
+    reader: impl Read,
+    writer: impl Write,
+    config: Config,
+    reporter: Option<Box<dyn ProgressReporter>>,
+) -> Result<(), String> {
+    let reporter = reporter.as_deref().unwrap_or(&NoProgress);
+    let format = config.output_format;
+    let preserve_dpi = config.preserve_dpi;
+    let (img, dpi) = read_image_from(reader)?;
+    reporter.on_progress(Stage::Decoding, 1.0);
+    let mut svg = convert_reporting_to(img, config, reporter)?;
+    if let Some(dpi) = dpi.filter(|_| preserve_dpi) {
+        set_dpi(&mut svg, dpi);
+    }
+    write_svg_to(svg, writer, format, None)?;
+    reporter.on_progress(Stage::Serialization, 1.0);
+    Ok(())
+}
+
+/// Convert an in-memory `DynamicImage` into an SVG string, without touching the filesystem.
+/// `config.output_format` is ignored, and the string is left to be encoded as
+/// `config.output_encoding` by the caller.
+pub fn convert_dynamic_image_to_svg(
+    image: &DynamicImage,
+    config: Config,
+) -> Result<String, VTracerError> {
+    let img = dynamic_image_to_color_image(image);
+    let svg = convert(img, config).map_err(VTracerError::Conversion)?;
+    Ok(svg.to_string())
+}
+
+/// Convert raw pixels, 4 bytes per pixel in RGBA order and rows from the top, into an SVG
+/// string without going through an image codec. `config.output_format` is ignored.
+pub fn convert_rgba_to_svg(
+    width: u32,
+    height: u32,
+    rgba: &[u8],
+    config: Config,
+) -> Result<String, VTracerError> {
+    let (width, height) = (width as usize, height as usize);
+    let Some(len) = width
+        .checked_mul(height)
+        .and_then(|area| area.checked_mul(4))
+    else {
+        return Err(VTracerError::Conversion(format!(
+            "A {}x{} RGBA image is too large.",
+            width, height
+        )));
+    };
+    if rgba.len() != len {
+        return Err(VTracerError::Conversion(format!(
+            "{} bytes of pixel data do not make a {}x{} RGBA image.",
+            rgba.len(),
+            width,
+            height
+        )));
+    }
+    let img = ColorImage {
+        pixels: rgba.to_vec(),
+        width,
+        height,
+    };
+    let svg = convert(img, config).map_err(VTracerError::Conversion)?;
+    Ok(svg.to_string())
+}
+
+/// Convert an in-memory `DynamicImage` into one SVG string per color, each holding the paths
+/// of that color, in the order the colors first appear in the stacking order.
+/// `config.output_format` and `config.split_by_color` are ignored.
+pub fn convert_image_to_svg_layers(
+    image: &DynamicImage,
+    config: Config,
+) -> Result<Vec<(Rgba, String)>, VTracerError> {
+    let img = dynamic_image_to_color_image(image);
+    let svg = convert(img, config).map_err(VTracerError::Conversion)?;
+    Ok(svg
+        .split_by_color()
+        .into_iter()
+        .map(|(color, layer)| {
+            let color = Rgba {
+                r: color.r,
+                g: color.g,
+                b: color.b,
+                a: color.a,
+            };
+            (color, layer.to_string())
+        })
+        .collect())
+}
+
+/// As `convert_dynamic_image_to_svg`, also measuring the color layers of the result
+pub fn convert_image_to_svg_with_stats(
+    image: &DynamicImage,
+    config: Config,
+) -> Result<(String, ColorStats), VTracerError> {
+    let img = dynamic_image_to_color_image(image);
+    let svg = convert(img, config).map_err(VTracerError::Conversion)?;
+    let stats = ColorStats::from_svg(&svg);
+    Ok((svg.to_string(), stats))
+}
+
+/// Convert an in-memory `DynamicImage` into paths, for writing formats other than SVG.
+/// `config.output_format` is ignored.
+pub fn convert_image_to_paths(
+    image: &DynamicImage,
+    config: Config,
+) -> Result<Vec<VectorPath>, VTracerError> {
+    let img = dynamic_image_to_color_image(image);
+    let svg = convert(img, config).map_err(VTracerError::Conversion)?;
+    Ok(VectorPath::from_svg(&svg))
+}
+
+/// Convert an in-memory `DynamicImage` into an SVG string on Tokio's blocking thread pool,
+/// so the conversion does not block the async executor. `config.output_format` is ignored.
+#[cfg(feature = "tokio")]
+pub async fn convert_async(image: DynamicImage, config: Config) -> Result<String, VTracerError> {
+    tokio::task::spawn_blocking(move || convert_dynamic_image_to_svg(&image, config))
+        .await
+        .map_err(|err| VTracerError::Conversion(format!("The conversion task failed: {}", err)))?
 }
 
 fn color_exists_in_image(img: &ColorImage, color: Color) -> bool {
@@ -48,7 +455,7 @@ fn color_exists_in_image(img: &ColorImage, color: Color) -> bool {
     false
 }
 
-fn find_unused_color_in_image(img: &ColorImage) -> Result<Color, String> {
+fn find_unused_color_in_image(img: &ColorImage, seed: Option<u64>) -> Result<Color, String> {
     let special_colors = IntoIterator::into_iter([
         Color::new(255, 0, 0),
         Color::new(0, 255, 0),
@@ -57,7 +464,10 @@ fn find_unused_color_in_image(img: &ColorImage) -> Result<Color, String> {
         Color::new(0, 255, 255),
         Color::new(255, 0, 255),
     ]);
-    let rng = Rng::new();
+    let rng = match seed {
+        Some(seed) => Rng::with_seed(seed),
+        None => Rng::new(),
+    };
     let random_colors =
         (0..NUM_UNUSED_COLOR_ITERATIONS).map(|_| Color::new(rng.u8(..), rng.u8(..), rng.u8(..)));
     for color in special_colors.chain(random_colors) {
@@ -70,6 +480,305 @@ fn find_unused_color_in_image(img: &ColorImage) -> Result<Color, String> {
     ))
 }
 
+fn encode_png(img: &ColorImage) -> Result<EmbeddedImage, String> {
+    let mut png = vec![];
+    PngEncoder::new(&mut png)
+        .encode(
+            &img.pixels,
+            img.width as u32,
+            img.height as u32,
+            ColorType::Rgba8,
+        )
+        .map_err(|_| String::from("Cannot encode the original image as PNG."))?;
+    Ok(EmbeddedImage {
+        png,
+        width: img.width,
+        height: img.height,
+    })
+}
+
+/// `img` downscaled with a Lanczos filter to fit within `width` by `height`, keeping its
+/// aspect ratio, or as it is if it already fits
+fn fit_within(img: ColorImage, width: u32, height: u32) -> Result<ColorImage, String> {
+    let (old_width, old_height) = (img.width as u32, img.height as u32);
+    let ratio = (width as f64 / old_width as f64).min(height as f64 / old_height as f64);
+    if ratio >= 1.0 {
+        return Ok(img);
+    }
+    let new_width = ((old_width as f64 * ratio).round() as u32).max(1);
+    let new_height = ((old_height as f64 * ratio).round() as u32).max(1);
+    log::debug!(
+        "Resizing from {}x{} to {}x{}",
+        old_width,
+        old_height,
+        new_width,
+        new_height
+    );
+    let buffer = RgbaImage::from_raw(old_width, old_height, img.pixels)
+        .ok_or_else(|| String::from("Cannot resize the image."))?;
+    let resized = imageops::resize(&buffer, new_width, new_height, FilterType::Lanczos3);
+    Ok(ColorImage {
+        pixels: resized.into_raw(),
+        width: new_width as usize,
+        height: new_height as usize,
+    })
+}
+
+/// The part of `img` within `region`
+fn crop(img: &ColorImage, region: Rect) -> Result<ColorImage, String> {
+    let (x, y) = (region.x as usize, region.y as usize);
+    let (width, height) = (region.width as usize, region.height as usize);
+    if x + width > img.width || y + height > img.height {
+        return Err(format!(
+            "The region {} lies outside of the {}x{} image.",
+            region, img.width, img.height
+        ));
+    }
+    let mut cropped = ColorImage::new_w_h(width, height);
+    for row in 0..height {
+        let start = ((y + row) * img.width + x) * 4;
+        cropped.pixels[row * width * 4..(row + 1) * width * 4]
+            .copy_from_slice(&img.pixels[start..start + width * 4]);
+    }
+    Ok(cropped)
+}
+
+/// Pad `img` with `fill` or crop it, centered, to the aspect ratio `ratio`
+fn fit_aspect_ratio(
+    img: ColorImage,
+    ratio: AspectRatio,
+    fit: AspectRatioFit,
+    fill: Color,
+) -> ColorImage {
+    let (width, height) = (img.width, img.height);
+    let (ratio_width, ratio_height) = (ratio.width as f64, ratio.height as f64);
+    let too_wide = width as f64 * ratio_height > height as f64 * ratio_width;
+    let fitted_width = || ((height as f64 * ratio_width / ratio_height).round() as usize).max(1);
+    let fitted_height = || ((width as f64 * ratio_height / ratio_width).round() as usize).max(1);
+    let (new_width, new_height) = match (fit, too_wide) {
+        (AspectRatioFit::Pad, true) | (AspectRatioFit::Crop, false) => (width, fitted_height()),
+        (AspectRatioFit::Pad, false) | (AspectRatioFit::Crop, true) => (fitted_width(), height),
+    };
+    if (new_width, new_height) == (width, height) {
+        return img;
+    }
+
+    // Offset of the old image within the new one, negative when cropping
+    let offset_x = (new_width as isize - width as isize) / 2;
+    let offset_y = (new_height as isize - height as isize) / 2;
+    let mut fitted = ColorImage::new_w_h(new_width, new_height);
+    for y in 0..new_height {
+        for x in 0..new_width {
+            let (old_x, old_y) = (x as isize - offset_x, y as isize - offset_y);
+            let inside =
+                (0..width as isize).contains(&old_x) && (0..height as isize).contains(&old_y);
+            let color = if inside {
+                img.get_pixel(old_x as usize, old_y as usize)
+            } else {
+                fill
+            };
+            fitted.set_pixel(x, y, &color);
+        }
+    }
+    fitted
+}
+
+/// Snap every pixel that blends the two most common colors of its orthogonal neighbours
+/// to the closer of the two, so anti-aliased edges do not form clusters of their own
+fn remove_fringes(img: ColorImage) -> ColorImage {
+    let (width, height) = (img.width, img.height);
+    let mut snapped = img.clone();
+    for y in 0..height {
+        for x in 0..width {
+            let neighbours = [
+                (x > 0).then(|| (x - 1, y)),
+                (x + 1 < width).then(|| (x + 1, y)),
+                (y > 0).then(|| (x, y - 1)),
+                (y + 1 < height).then(|| (x, y + 1)),
+            ];
+            let mut counts: Vec<(Color, usize)> = vec![];
+            for (nx, ny) in neighbours.into_iter().flatten() {
+                let color = img.get_pixel(nx, ny);
+                match counts.iter_mut().find(|(c, _)| *c == color) {
+                    Some((_, count)) => *count += 1,
+                    None => counts.push((color, 1)),
+                }
+            }
+            if counts.len() < 2 {
+                continue;
+            }
+            counts.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+            let (a, b) = (counts[0].0, counts[1].0);
+            let pixel = img.get_pixel(x, y);
+            if pixel == a || pixel == b {
+                continue;
+            }
+            if let Some(t) = blend_factor(pixel, a, b) {
+                snapped.set_pixel(x, y, if t < 0.5 { &a } else { &b });
+            }
+        }
+    }
+    snapped
+}
+
+/// The `t` for which `pixel` is closest to `a * (1 - t) + b * t`, if `pixel` lies between
+/// `a` and `b` within `FRINGE_TOLERANCE`
+fn blend_factor(pixel: Color, a: Color, b: Color) -> Option<f64> {
+    let channels = |c: Color| [c.r as f64, c.g as f64, c.b as f64, c.a as f64];
+    let (p, a, b) = (channels(pixel), channels(a), channels(b));
+    let ab: Vec<f64> = (0..4).map(|i| b[i] - a[i]).collect();
+    let ap: Vec<f64> = (0..4).map(|i| p[i] - a[i]).collect();
+    let length_squared: f64 = ab.iter().map(|v| v * v).sum();
+    let t = ab.iter().zip(&ap).map(|(u, v)| u * v).sum::<f64>() / length_squared;
+    if !(0.0..=1.0).contains(&t) {
+        return None;
+    }
+    let distance_squared: f64 = (0..4).map(|i| (ap[i] - ab[i] * t).powi(2)).sum();
+    (distance_squared <= FRINGE_TOLERANCE * FRINGE_TOLERANCE).then_some(t)
+}
+
+/// Invert the color channels of every pixel, keeping alpha untouched
+fn invert(mut img: ColorImage) -> ColorImage {
+    for pixel in img.pixels.chunks_exact_mut(4) {
+        pixel[0] = !pixel[0];
+        pixel[1] = !pixel[1];
+        pixel[2] = !pixel[2];
+    }
+    img
+}
+
+/// Make the pixels of alpha below `threshold` fully transparent
+fn clear_below_alpha(mut img: ColorImage, threshold: u8) -> ColorImage {
+    for pixel in img.pixels.chunks_exact_mut(4) {
+        if pixel[3] < threshold {
+            pixel[3] = 0;
+        }
+    }
+    img
+}
+
+/// Turn the fully transparent pixels white, which binary mode leaves out of the paths
+fn whiten_transparent(mut img: ColorImage) -> ColorImage {
+    for pixel in img.pixels.chunks_exact_mut(4) {
+        if pixel[3] == 0 {
+            pixel[..3].fill(255);
+        }
+    }
+    img
+}
+
+/// Blend every pixel over opaque white by its alpha, leaving the image opaque
+fn composite_over_white(mut img: ColorImage) -> ColorImage {
+    for pixel in img.pixels.chunks_exact_mut(4) {
+        let alpha = pixel[3] as u32;
+        for channel in &mut pixel[..3] {
+            *channel = ((*channel as u32 * alpha + 255 * (255 - alpha) + 127) / 255) as u8;
+        }
+        pixel[3] = 255;
+    }
+    img
+}
+
+/// Raise the color channels of every pixel, as fractions of 255, to the power `gamma`
+fn apply_gamma(mut img: ColorImage, gamma: f64) -> ColorImage {
+    if gamma == 1.0 {
+        return img;
+    }
+    let table: Vec<u8> = (0..=255)
+        .map(|v| ((v as f64 / 255.0).powf(gamma) * 255.0).round() as u8)
+        .collect();
+    for pixel in img.pixels.chunks_exact_mut(4) {
+        pixel[0] = table[pixel[0] as usize];
+        pixel[1] = table[pixel[1] as usize];
+        pixel[2] = table[pixel[2] as usize];
+    }
+    img
+}
+
+/// Replace every pixel with its luminance under the red, green and blue `weights`,
+/// keeping alpha untouched
+fn to_grayscale(mut img: ColorImage, weights: (f64, f64, f64)) -> ColorImage {
+    let (r, g, b) = weights;
+    for pixel in img.pixels.chunks_exact_mut(4) {
+        let luma = (r * pixel[0] as f64 + g * pixel[1] as f64 + b * pixel[2] as f64)
+            .round()
+            .clamp(0.0, 255.0) as u8;
+        pixel[0] = luma;
+        pixel[1] = luma;
+        pixel[2] = luma;
+    }
+    img
+}
+
+/// Luminance of an sRGB color (Rec. 601)
+pub(crate) fn luminance(r: u8, g: u8, b: u8) -> u8 {
+    (0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64).round() as u8
+}
+
+/// Threshold the luminance of `img` with Floyd-Steinberg error diffusion: the difference
+/// between each pixel and the black or white it becomes is spread over the pixels right
+/// and below it, in scanline order
+fn dither(img: &ColorImage, threshold: u8) -> BinaryImage {
+    let (width, height) = (img.width, img.height);
+    let mut values: Vec<f64> = img
+        .pixels
+        .chunks_exact(4)
+        .map(|pixel| luminance(pixel[0], pixel[1], pixel[2]) as f64)
+        .collect();
+    let mut binary = BinaryImage::new_w_h(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let value = values[y * width + x];
+            let is_black = value < threshold as f64;
+            binary.set_pixel(x, y, is_black);
+            let error = value - if is_black { 0.0 } else { 255.0 };
+            let mut spread = |dx: isize, dy: usize, weight: f64| {
+                let nx = x as isize + dx;
+                if nx >= 0 && (nx as usize) < width && y + dy < height {
+                    values[(y + dy) * width + nx as usize] += error * weight;
+                }
+            };
+            spread(1, 0, 7.0 / 16.0);
+            spread(-1, 1, 3.0 / 16.0);
+            spread(0, 1, 5.0 / 16.0);
+            spread(1, 1, 1.0 / 16.0);
+        }
+    }
+    binary
+}
+
+/// The luminance threshold that best separates the pixels of `img` into two classes,
+/// by maximizing the between-class variance (Otsu's method)
+fn otsu_threshold(img: &ColorImage) -> u8 {
+    let mut histogram = [0usize; 256];
+    for pixel in img.pixels.chunks_exact(4) {
+        histogram[luminance(pixel[0], pixel[1], pixel[2]) as usize] += 1;
+    }
+    let total = img.width * img.height;
+    let sum: f64 = (0..256).map(|i| i as f64 * histogram[i] as f64).sum();
+
+    let (mut best_threshold, mut best_variance) = (128, 0.0);
+    let (mut weight_below, mut sum_below) = (0usize, 0.0);
+    // Pixels of a luminance below `threshold` make the dark class
+    for threshold in 1..256 {
+        weight_below += histogram[threshold - 1];
+        sum_below += (threshold - 1) as f64 * histogram[threshold - 1] as f64;
+        let weight_above = total - weight_below;
+        if weight_below == 0 || weight_above == 0 {
+            continue;
+        }
+        let mean_below = sum_below / weight_below as f64;
+        let mean_above = (sum - sum_below) / weight_above as f64;
+        let variance =
+            weight_below as f64 * weight_above as f64 * (mean_below - mean_above).powi(2);
+        if variance > best_variance {
+            best_variance = variance;
+            best_threshold = threshold;
+        }
+    }
+    best_threshold as u8
+}
+
 fn should_key_image(img: &ColorImage) -> bool {
     if img.width == 0 || img.height == 0 {
         return false;
@@ -99,12 +808,48 @@ fn should_key_image(img: &ColorImage) -> bool {
     false
 }
 
-fn color_image_to_svg(mut img: ColorImage, config: ConverterConfig) -> Result<SvgFile, String> {
+/// Run the clustering incrementally, reporting the pixel grouping pass as `Stage::Quantization`
+/// and the hierarchical merging pass as `Stage::Clustering`.
+/// Completion of `Stage::Clustering` is left to the caller.
+fn run_with_progress(runner: Runner, reporter: &dyn ProgressReporter) -> Clusters {
+    let mut builder = runner.start();
+    let mut stage = Stage::Quantization;
+    while !builder.tick() {
+        let progress = builder.progress() as f32 / 100.0;
+        if progress < 0.5 {
+            reporter.on_progress(Stage::Quantization, progress * 2.0);
+        } else {
+            if stage == Stage::Quantization {
+                reporter.on_progress(Stage::Quantization, 1.0);
+                stage = Stage::Clustering;
+            }
+            reporter.on_progress(Stage::Clustering, (progress * 2.0 - 1.0).min(0.99));
+        }
+    }
+    if stage == Stage::Quantization {
+        reporter.on_progress(Stage::Quantization, 1.0);
+    }
+    builder.result()
+}
+
+fn color_image_to_svg(
+    img: ColorImage,
+    config: &ConverterConfig,
+    reporter: &dyn ProgressReporter,
+) -> Result<SvgFile, String> {
+    // Clustered in Lab, with the path colors back in sRGB before their count is reduced
+    let lab =
+        matches!(config.color_mode, ColorMode::Color) && config.color_space == ColorSpace::Lab;
+    let mut img = if lab { to_lab(img) } else { img };
     let width = img.width;
     let height = img.height;
 
-    let key_color = if should_key_image(&img) {
-        let key_color = find_unused_color_in_image(&img)?;
+    // Any transparent pixel is left out with an alpha threshold, not only large areas of them
+    let has_transparency = || img.pixels.chunks_exact(4).any(|pixel| pixel[3] == 0);
+    let key_color = if should_key_image(&img) || (config.alpha_threshold > 0 && has_transparency())
+    {
+        let key_color = find_unused_color_in_image(&img, config.random_seed)?;
+        log::trace!("Keying transparent pixels as {}", key_color.to_hex_string());
         for y in 0..height {
             for x in 0..width {
                 if img.get_pixel(x, y).a == 0 {
@@ -139,7 +884,7 @@ fn color_image_to_svg(mut img: ColorImage, config: ConverterConfig) -> Result<Sv
         img,
     );
 
-    let mut clusters = runner.run();
+    let mut clusters = run_with_progress(runner, reporter);
 
     match config.hierarchical {
         Hierarchical::Stacked => {}
@@ -152,7 +897,7 @@ fn color_image_to_svg(mut img: ColorImage, config: ConverterConfig) -> Result<Sv
                     hierarchical: 64,
                     batch_size: 25600,
                     good_min_area: 0,
-                    good_max_area: (image.width * image.height) as usize,
+                    good_max_area: (image.width * image.height),
                     is_same_color_a: 0,
                     is_same_color_b: 1,
                     deepen_diff: 0,
@@ -165,12 +910,27 @@ fn color_image_to_svg(mut img: ColorImage, config: ConverterConfig) -> Result<Sv
             clusters = runner.run();
         }
     }
+    reporter.on_progress(Stage::Clustering, 1.0);
 
     let view = clusters.view();
+    let limited = limit_layers(&view, config.layer_limit);
+    let mut layers: Vec<&Cluster> = limited.iter().map(|layer| layer.as_ref()).collect();
+    if let Some(limit) = config.max_paths_per_cluster {
+        let precision_loss = config.color_precision_loss;
+        layers = keep_largest_per_color(layers, limit, |cluster| {
+            let color = cluster.residue_color();
+            // Colors within the color precision belong to one layer
+            let key = [color.r, color.g, color.b, color.a].map(|c| c >> precision_loss);
+            (key, cluster.area())
+        });
+    }
+    log::debug!(
+        "Clustered into {} clusters, tracing {} layers",
+        view.clusters_output.len(),
+        layers.len()
+    );
 
-    let mut svg = SvgFile::new(width, height, config.path_precision);
-    for &cluster_index in view.clusters_output.iter().rev() {
-        let cluster = view.get_cluster(cluster_index);
+    let build_path = |cluster: &Cluster| {
         let paths = if matches!(config.mode, PathSimplifyMode::Spline)
             && cluster.rect.width() < SMALL_CIRCLE
             && cluster.rect.height() < SMALL_CIRCLE
@@ -183,72 +943,771 @@ fn color_image_to_svg(mut img: ColorImage, config: ConverterConfig) -> Result<Sv
             ));
             paths
         } else {
-            cluster.to_compound_path(
+            let paths = cluster.to_compound_path(
                 &view,
                 false,
-                config.mode,
+                trace_mode(config.mode),
                 config.corner_threshold,
                 config.length_threshold,
                 config.max_iterations,
                 config.splice_threshold,
-            )
+            );
+            fit_curves(paths, config)
         };
-        svg.add_path(paths, cluster.residue_color());
+        (paths, cluster.residue_color())
+    };
+
+    let mut svg = SvgFile::new(width, height, config.path_precision);
+    if config.parallel {
+        // Collecting an indexed parallel iterator keeps the layers in stacking order.
+        // The reporter is not thread safe, so only completion is reported.
+        let build_paths = || -> Vec<_> {
+            layers
+                .par_iter()
+                .rev()
+                .map(|&cluster| build_path(cluster))
+                .collect()
+        };
+        let paths = match config.cpu_threads {
+            // The global pool is used when it has the right size, as set up by the command line
+            Some(threads) if threads != rayon::current_num_threads() => {
+                match ThreadPoolBuilder::new().num_threads(threads).build() {
+                    Ok(pool) => pool.install(build_paths),
+                    Err(_) => return Err(String::from("Cannot start the conversion threads.")),
+                }
+            }
+            _ => build_paths(),
+        };
+        for (paths, color) in paths {
+            svg.add_path(paths, color);
+        }
+    } else {
+        let num_clusters = layers.len();
+        for (i, &cluster) in layers.iter().rev().enumerate() {
+            reporter.on_progress(Stage::PathBuilding, i as f32 / num_clusters as f32);
+            let (paths, color) = build_path(cluster);
+            svg.add_path(paths, color);
+        }
+    }
+    reporter.on_progress(Stage::PathBuilding, 1.0);
+    if lab {
+        for path in svg.paths.iter_mut() {
+            path.color = lab_to_color(path.color);
+        }
+    }
+
+    if let Some(max_colors) = config.max_colors {
+        let layers: Vec<_> = svg
+            .paths
+            .iter()
+            .zip(layers.iter().rev())
+            .map(|(path, cluster)| (path.color, cluster.area()))
+            .collect();
+        for (path, color) in svg.paths.iter_mut().zip(reduce_colors(&layers, max_colors)) {
+            path.color = color;
+        }
     }
 
     Ok(svg)
 }
 
-fn binary_image_to_svg(img: ColorImage, config: ConverterConfig) -> Result<SvgFile, String> {
-    let img = img.to_binary_image(|x| x.r < 128);
+/// `items` in their order, less the smallest ones of each color beyond `limit`.
+/// `color_and_area` gives the color key and area of an item.
+fn keep_largest_per_color<T: Copy, K: Eq + Hash>(
+    items: Vec<T>,
+    limit: usize,
+    color_and_area: impl Fn(T) -> (K, usize),
+) -> Vec<T> {
+    let mut colors: HashMap<K, Vec<(usize, usize)>> = HashMap::new();
+    for (position, &item) in items.iter().enumerate() {
+        let (color, area) = color_and_area(item);
+        colors.entry(color).or_default().push((area, position));
+    }
+    let mut keep = vec![true; items.len()];
+    for mut group in colors.into_values().filter(|group| group.len() > limit) {
+        // Largest first, with ties broken by position
+        group.sort_unstable_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        for &(_, position) in &group[limit..] {
+            keep[position] = false;
+        }
+    }
+    items
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(item, keep)| keep.then_some(item))
+        .collect()
+}
+
+/// The output clusters of `view` in stacking order. Beyond `limit`, the smallest ones are
+/// merged into the neighbour they share the most pixels with, so that no gaps open up.
+fn limit_layers<'a>(view: &'a ClustersView, limit: Option<usize>) -> Vec<Cow<'a, Cluster>> {
+    let mut layers: Vec<_> = view
+        .clusters_output
+        .iter()
+        .map(|&index| Cow::Borrowed(view.get_cluster(index)))
+        .collect();
+    let Some(limit) = limit.filter(|&limit| layers.len() > limit) else {
+        return layers;
+    };
+    // Smallest first, with ties broken by position in `clusters_output` from the back
+    let mut by_area: Vec<usize> = (0..layers.len()).collect();
+    by_area.sort_unstable_by(|&a, &b| layers[a].area().cmp(&layers[b].area()).then(b.cmp(&a)));
+
+    // The topmost layer at each pixel, as painted
+    let width = view.width as usize;
+    let height = view.height as usize;
+    let mut owner = vec![usize::MAX; width * height];
+    for (position, layer) in layers.iter().enumerate().rev() {
+        for &i in &layer.indices {
+            owner[i as usize] = position;
+        }
+    }
+
+    // Each merged layer points at the layer it went into; dropped layers had no neighbour
+    let mut merged_into: Vec<usize> = (0..layers.len()).collect();
+    let mut dropped = vec![false; layers.len()];
+    let find = |merged_into: &[usize], mut position: usize| {
+        while merged_into[position] != position {
+            position = merged_into[position];
+        }
+        position
+    };
+    for &position in &by_area[..layers.len() - limit] {
+        let mut shared: HashMap<usize, usize> = HashMap::new();
+        for &i in &layers[position].indices {
+            let (x, y) = (i as usize % width, i as usize / width);
+            let mut around = vec![i as usize];
+            if x > 0 {
+                around.push(i as usize - 1);
+            }
+            if x + 1 < width {
+                around.push(i as usize + 1);
+            }
+            if y > 0 {
+                around.push(i as usize - width);
+            }
+            if y + 1 < height {
+                around.push(i as usize + width);
+            }
+            for j in around.into_iter().filter(|&j| owner[j] != usize::MAX) {
+                let other = find(&merged_into, owner[j]);
+                if other != position && !dropped[other] {
+                    *shared.entry(other).or_default() += 1;
+                }
+            }
+        }
+        // Most shared pixels first, with ties going to the layer on top
+        match shared
+            .into_iter()
+            .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))
+        {
+            Some((other, _)) => merged_into[position] = other,
+            None => dropped[position] = true,
+        }
+    }
+
+    let mut absorbed: HashMap<usize, Vec<usize>> = HashMap::new();
+    for position in 0..layers.len() {
+        let into = find(&merged_into, position);
+        if into != position {
+            absorbed.entry(into).or_default().push(position);
+        }
+    }
+    for (into, positions) in absorbed {
+        let mut layer = layers[into].clone().into_owned();
+        for position in positions {
+            layer.indices.extend_from_slice(&layers[position].indices);
+            layer.rect.merge(layers[position].rect);
+        }
+        // Stacked layers cover the layers above them already
+        layer.indices.sort_unstable();
+        layer.indices.dedup();
+        layers[into] = Cow::Owned(layer);
+    }
+    layers
+        .into_iter()
+        .enumerate()
+        .filter(|&(position, _)| merged_into[position] == position && !dropped[position])
+        .map(|(_, layer)| layer)
+        .collect()
+}
+
+fn binary_image_to_svg(
+    img: ColorImage,
+    config: &ConverterConfig,
+    reporter: &dyn ProgressReporter,
+) -> Result<SvgFile, String> {
+    // With an alpha threshold, transparent pixels are background whatever their color
+    let img = if config.alpha_threshold > 0 {
+        whiten_transparent(img)
+    } else {
+        img
+    };
+    let img = if config.canny_preprocess {
+        canny_edges(
+            &img,
+            config.canny_low_threshold,
+            config.canny_high_threshold,
+        )
+    } else {
+        let threshold = config
+            .binary_threshold
+            .unwrap_or_else(|| otsu_threshold(&img));
+        if config.dither {
+            dither(&img, threshold)
+        } else {
+            img.to_binary_image(|x| luminance(x.r, x.g, x.b) < threshold)
+        }
+    };
     let width = img.width;
     let height = img.height;
+    reporter.on_progress(Stage::Quantization, 1.0);
 
     let clusters = img.to_clusters(false);
+    log::debug!("Clustered into {} clusters", clusters.len());
+    reporter.on_progress(Stage::Clustering, 1.0);
+
+    let mut kept: Vec<usize> = (0..clusters.len())
+        .filter(|&i| clusters.get_cluster(i).size() >= config.filter_speckle_area)
+        .collect();
+    if let Some(limit) = config.max_paths_per_cluster {
+        // Every cluster is black
+        kept = keep_largest_per_color(kept, limit, |i| ((), clusters.get_cluster(i).size()));
+    }
 
     let mut svg = SvgFile::new(width, height, config.path_precision);
-    for i in 0..clusters.len() {
-        let cluster = clusters.get_cluster(i);
-        if cluster.size() >= config.filter_speckle_area {
-            let paths = cluster.to_compound_path(
-                config.mode,
-                config.corner_threshold,
-                config.length_threshold,
-                config.max_iterations,
-                config.splice_threshold,
-            );
-            svg.add_path(paths, Color::color(&ColorName::Black));
-        }
+    svg.stroke_width = config.stroke_width;
+    svg.close_paths = config.close_paths;
+    for (n, &i) in kept.iter().enumerate() {
+        reporter.on_progress(Stage::PathBuilding, n as f32 / kept.len() as f32);
+        let paths = clusters.get_cluster(i).to_compound_path(
+            trace_mode(config.mode),
+            config.corner_threshold,
+            config.length_threshold,
+            config.max_iterations,
+            config.splice_threshold,
+        );
+        svg.add_path(fit_curves(paths, config), Color::color(&ColorName::Black));
     }
+    reporter.on_progress(Stage::PathBuilding, 1.0);
 
     Ok(svg)
 }
 
-fn read_image(input_path: &Path) -> Result<ColorImage, String> {
-    let img = image::open(input_path);
-    let img = match img {
-        Ok(file) => file.to_rgba8(),
+/// The visioncortex mode used to trace the outlines.
+/// Bézier curves are fitted to the traced polygons afterwards by `fit_curves`.
+fn trace_mode(mode: PathSimplifyMode) -> visioncortex::PathSimplifyMode {
+    match mode {
+        PathSimplifyMode::None => visioncortex::PathSimplifyMode::None,
+        PathSimplifyMode::Polygon | PathSimplifyMode::Bezier => {
+            visioncortex::PathSimplifyMode::Polygon
+        }
+        PathSimplifyMode::Spline => visioncortex::PathSimplifyMode::Spline,
+    }
+}
+
+fn fit_curves(paths: CompoundPath, config: &ConverterConfig) -> CompoundPath {
+    match config.mode {
+        PathSimplifyMode::Bezier => {
+            fit_compound_path(&paths, config.bezier_tolerance, config.corner_threshold)
+        }
+        _ => paths,
+    }
+}
+
+/// Total arc length of all subpaths, with splines flattened into short line segments
+pub(crate) fn compound_path_length(path: &CompoundPath) -> f64 {
+    const SPLINE_STEPS: usize = 8;
+    let polyline_length = |points: &[PointF64]| -> f64 {
+        points
+            .windows(2)
+            .map(|pair| pair[0].distance_to(pair[1]))
+            .sum()
+    };
+    path.paths
+        .iter()
+        .map(|element| match element {
+            CompoundPathElement::PathI32(p) => {
+                let points: Vec<_> = p.path.iter().map(|p| p.to_point_f64()).collect();
+                polyline_length(&points)
+            }
+            CompoundPathElement::PathF64(p) => polyline_length(&p.path),
+            CompoundPathElement::Spline(s) => s
+                .points
+                .windows(4)
+                .step_by(3)
+                .map(|curve| {
+                    let points: Vec<_> = (0..=SPLINE_STEPS)
+                        .map(|i| cubic_bezier_point(curve, i as f64 / SPLINE_STEPS as f64))
+                        .collect();
+                    polyline_length(&points)
+                })
+                .sum(),
+        })
+        .sum()
+}
+
+pub(crate) fn cubic_bezier_point(curve: &[PointF64], t: f64) -> PointF64 {
+    let u = 1.0 - t;
+    let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+    PointF64::new(
+        a * curve[0].x + b * curve[1].x + c * curve[2].x + d * curve[3].x,
+        a * curve[0].y + b * curve[1].y + c * curve[2].y + d * curve[3].y,
+    )
+}
+
+/// The decoded image, and its resolution in dots per inch if it has one
+fn read_image(input_path: &Path) -> Result<(ColorImage, Option<f64>), String> {
+    let buffer = match std::fs::read(input_path) {
+        Ok(buffer) => buffer,
         Err(_) => return Err(String::from("No image file found at specified input path")),
     };
+    let img = decode_image(&buffer, Some(input_path))?;
 
+    Ok((dynamic_image_to_color_image(&img), png_dpi(&buffer)))
+}
+
+fn read_image_from(mut reader: impl Read) -> Result<(ColorImage, Option<f64>), String> {
+    let mut buffer = vec![];
+    if reader.read_to_end(&mut buffer).is_err() {
+        return Err(String::from("Cannot read input image."));
+    }
+    let img = decode_image(&buffer, None)?;
+
+    Ok((dynamic_image_to_color_image(&img), png_dpi(&buffer)))
+}
+
+/// Size the SVG as the image printed at `dpi`, in millimeters unless a unit is set
+fn set_dpi(svg: &mut SvgFile, dpi: f64) {
+    log::debug!("Sizing the SVG at {} dpi", dpi);
+    svg.dpi = dpi;
+    svg.unit.get_or_insert(Unit::Mm);
+}
+
+pub(crate) fn dynamic_image_to_color_image(img: &DynamicImage) -> ColorImage {
+    let img = img.to_rgba8();
     let (width, height) = (img.width() as usize, img.height() as usize);
-    let img = ColorImage {
-        pixels: img.as_raw().to_vec(),
+    ColorImage {
+        pixels: img.into_raw(),
         width,
         height,
-    };
-
-    Ok(img)
+    }
 }
 
-fn write_svg(svg: SvgFile, output_path: &Path) -> Result<(), String> {
+pub(crate) fn write_svg(
+    svg: SvgFile,
+    output_path: &Path,
+    format: OutputFormat,
+    title: Option<&str>,
+) -> Result<(), String> {
     let out_file = File::create(output_path);
-    let mut out_file = match out_file {
+    let out_file = match out_file {
         Ok(file) => file,
         Err(_) => return Err(String::from("Cannot create output file.")),
     };
 
-    write!(&mut out_file, "{}", svg).expect("failed to write file.");
+    write_svg_to(svg, BufWriter::new(out_file), format, title)
+}
 
-    Ok(())
+/// `title` is only used by formats which carry document metadata
+fn write_svg_to(
+    svg: SvgFile,
+    mut writer: impl Write,
+    format: OutputFormat,
+    title: Option<&str>,
+) -> Result<(), String> {
+    let result = match format {
+        OutputFormat::Svg => writer.write_all(&svg.to_bytes()),
+        OutputFormat::Dxf => write!(writer, "{}", DxfFile::new(&svg)),
+        OutputFormat::Eps => write!(writer, "{}", EpsFile::new(&svg)),
+        OutputFormat::SvgCompressed => {
+            let mut encoder = GzEncoder::new(&mut writer, Compression::best());
+            (encoder.write_all(&svg.to_bytes())).and_then(|_| encoder.finish().map(|_| ()))
+        }
+        OutputFormat::Pdf => {
+            let pdf = match svg.title.as_deref().or(title) {
+                Some(title) => PdfFile::new(&svg).with_title(title),
+                None => PdfFile::new(&svg),
+            };
+            writer.write_all(&pdf.to_bytes())
+        }
+        OutputFormat::PngTrace => writer.write_all(&TracePng::new(&svg).to_bytes()?),
+        OutputFormat::Canvas => {
+            let canvas = match svg.title.as_deref().or(title) {
+                Some(title) => CanvasFile::new(&svg).with_title(title),
+                None => CanvasFile::new(&svg),
+            };
+            write!(writer, "{}", canvas)
+        }
+        OutputFormat::Rust => write!(writer, "{}", RustFile::new(&svg)),
+        OutputFormat::Json => writer.write_all(&JsonFile::new(&svg).to_bytes()?),
+    };
+    match result.and_then(|_| writer.flush()) {
+        Ok(()) => Ok(()),
+        Err(_) => Err(String::from("Cannot write output file.")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::svg_to_paths;
+    use visioncortex::CompoundPathElement;
+
+    /// An opaque image with the color `pixel(x, y)` at every pixel
+    fn test_image(
+        width: usize,
+        height: usize,
+        pixel: impl Fn(usize, usize) -> [u8; 3],
+    ) -> ColorImage {
+        let mut pixels = Vec::with_capacity(width * height * 4);
+        for y in 0..height {
+            for x in 0..width {
+                pixels.extend(pixel(x, y));
+                pixels.push(255);
+            }
+        }
+        ColorImage {
+            pixels,
+            width,
+            height,
+        }
+    }
+
+    /// Red and blue squares nested on white, with a green disc across the red one
+    fn nested_shapes() -> ColorImage {
+        test_image(48, 48, |x, y| {
+            let (dx, dy) = (x as f64 - 34.0, y as f64 - 34.0);
+            if dx * dx + dy * dy < 64.0 {
+                [0, 160, 0]
+            } else if (18..30).contains(&x) && (18..30).contains(&y) {
+                [0, 0, 255]
+            } else if (8..40).contains(&x) && (8..40).contains(&y) {
+                [255, 0, 0]
+            } else {
+                [255, 255, 255]
+            }
+        })
+    }
+
+    /// Number of paths covering the center of every pixel, by the nonzero rule SVG fills with.
+    /// Only understands polygons, as traced in pixel and polygon mode.
+    fn coverage(svg: &SvgFile) -> Vec<usize> {
+        let polygons: Vec<Vec<Vec<PointF64>>> = svg_to_paths(&svg.to_string())
+            .iter()
+            .map(|path| {
+                let compound = path.to_compound_path().unwrap();
+                compound
+                    .paths
+                    .into_iter()
+                    .map(|element| match element {
+                        CompoundPathElement::PathF64(path) => path.path,
+                        _ => panic!("Expected polygons only"),
+                    })
+                    .collect()
+            })
+            .collect();
+        let mut counts = vec![0; svg.width * svg.height];
+        for y in 0..svg.height {
+            for x in 0..svg.width {
+                let center = PointF64::new(x as f64 + 0.5, y as f64 + 0.5);
+                counts[y * svg.width + x] = polygons
+                    .iter()
+                    .filter(|subpaths| {
+                        subpaths
+                            .iter()
+                            .map(|points| winding(points, center))
+                            .sum::<i32>()
+                            != 0
+                    })
+                    .count();
+            }
+        }
+        counts
+    }
+
+    fn winding(points: &[PointF64], p: PointF64) -> i32 {
+        let mut winding = 0;
+        for (a, b) in points.iter().zip(points.iter().cycle().skip(1)) {
+            let cross = (b.x - a.x) * (p.y - a.y) - (p.x - a.x) * (b.y - a.y);
+            if a.y <= p.y && b.y > p.y && cross > 0.0 {
+                winding += 1;
+            } else if a.y > p.y && b.y <= p.y && cross < 0.0 {
+                winding -= 1;
+            }
+        }
+        winding
+    }
+
+    fn config(hierarchical: Hierarchical, mode: PathSimplifyMode) -> Config {
+        Config {
+            hierarchical,
+            mode,
+            filter_speckle: 0,
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn min_path_length_drops_short_paths() {
+        // A large square among 2x2 specks, whose outlines are 8 pixels long
+        let img = test_image(40, 40, |x, y| {
+            if (20..36).contains(&x) && (20..36).contains(&y) || x % 6 < 2 && y % 6 < 2 && y < 18 {
+                [0, 0, 0]
+            } else {
+                [255, 255, 255]
+            }
+        });
+        let count = |min_path_length| {
+            let config = Config {
+                filter_speckle: 0,
+                mode: PathSimplifyMode::Polygon,
+                min_path_length,
+                ..Config::default()
+            };
+            convert(img.clone(), config).unwrap().paths.len()
+        };
+        let all = count(None);
+        let long = count(Some(20.0));
+        assert!(long < all, "{} paths of {}", long, all);
+        assert!(long > 0);
+    }
+
+    #[test]
+    fn grayscale_gamma_moves_the_binary_threshold() {
+        // Grey squares on white, darker to the left
+        let levels = [40, 80, 100, 150, 180, 200];
+        let img = test_image(70, 14, |x, y| {
+            if x % 12 >= 2 && y % 12 >= 2 && x / 12 < levels.len() && y < 12 {
+                [levels[x / 12]; 3]
+            } else {
+                [255, 255, 255]
+            }
+        });
+        let count = |grayscale_gamma| {
+            let config = Config {
+                color_mode: ColorMode::Binary,
+                grayscale_gamma,
+                ..config(Hierarchical::Stacked, PathSimplifyMode::Polygon)
+            };
+            convert(img.clone(), config).unwrap().paths.len()
+        };
+        // Brightened shadows turn white, darkened highlights turn black
+        let brightened = count(0.5);
+        let darkened = count(2.0);
+        assert!(
+            brightened < darkened,
+            "{} and {} paths",
+            brightened,
+            darkened
+        );
+        assert!(brightened > 0);
+    }
+
+    #[test]
+    fn polygon_mode_writes_straight_lines_only() {
+        for corner_threshold in [0, 30, 45, 60, 90, 135, 180] {
+            let config = Config {
+                corner_threshold,
+                ..config(Hierarchical::Stacked, PathSimplifyMode::Polygon)
+            };
+            let string = convert(nested_shapes(), config).unwrap().to_string();
+            assert!(string.contains(r#" d=""#));
+            for data in string.split(r#" d=""#).skip(1) {
+                let data = &data[..data.find('"').unwrap()];
+                assert!(
+                    !data.contains(['C', 'c', 'Q', 'q', 'S', 's', 'T', 't']),
+                    "Curves at corner_threshold {}: {}",
+                    corner_threshold,
+                    data
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn lab_colors_are_reduced_in_srgb() {
+        let colors = |color_space| {
+            let config = Config {
+                color_space,
+                max_colors: Some(3),
+                ..config(Hierarchical::Cutout, PathSimplifyMode::Polygon)
+            };
+            let svg = convert(nested_shapes(), config).unwrap();
+            svg.paths.iter().map(|path| path.color).collect::<Vec<_>>()
+        };
+        let rgb = colors(ColorSpace::Rgb);
+        let lab = colors(ColorSpace::Lab);
+        assert_eq!(rgb.len(), lab.len());
+        // Only rounding through Lab and back sets them apart
+        for (a, b) in rgb.iter().zip(&lab) {
+            let close = |x: u8, y: u8| x.abs_diff(y) <= 2;
+            assert!(
+                close(a.r, b.r) && close(a.g, b.g) && close(a.b, b.b),
+                "{} and {}",
+                a.to_hex_string(),
+                b.to_hex_string()
+            );
+        }
+    }
+
+    #[test]
+    fn path_sort_keeps_stacked_layers_in_order() {
+        // A red square inside a blue one, which sorting by hue would paint over it
+        let img = test_image(32, 32, |x, y| {
+            if (8..24).contains(&x) && (8..24).contains(&y) {
+                [255, 0, 0]
+            } else if (2..30).contains(&x) && (2..30).contains(&y) {
+                [0, 0, 255]
+            } else {
+                [255, 255, 255]
+            }
+        });
+        let colors = |hierarchical, path_sort| {
+            let config = Config {
+                path_sort,
+                ..config(hierarchical, PathSimplifyMode::Polygon)
+            };
+            let svg = convert(img.clone(), config).unwrap();
+            svg.paths
+                .iter()
+                .map(|path| path.color.to_hex_string())
+                .collect::<Vec<_>>()
+        };
+        let by_hue = ["#FFFFFF", "#FF0000", "#0000FF"];
+        let traced = colors(Hierarchical::Stacked, PathSort::None);
+        assert_ne!(traced, by_hue);
+        assert_eq!(colors(Hierarchical::Stacked, PathSort::Color), traced);
+        assert_eq!(colors(Hierarchical::Stacked, PathSort::Area), traced);
+        // Cutout paths do not overlap, so all of them are sorted
+        assert_eq!(colors(Hierarchical::Cutout, PathSort::Color), by_hue);
+    }
+
+    #[test]
+    fn oversized_rgba_is_rejected() {
+        let result = convert_rgba_to_svg(0x8000_0000, 0x8000_0000, &[], Config::default());
+        assert!(matches!(result, Err(VTracerError::Conversion(_))));
+    }
+
+    #[test]
+    fn markup_in_the_id_prefix_is_rejected() {
+        for prefix in ["a\"b", "<", "é"] {
+            let config = Config {
+                output_id_prefix: String::from(prefix),
+                ..Config::default()
+            };
+            assert!(convert(nested_shapes(), config.clone()).is_err());
+            assert!(!config.validate().is_empty());
+            let built = Config::builder()
+                .output_id_prefix(prefix.to_owned())
+                .build();
+            assert!(built.is_err());
+        }
+    }
+
+    #[test]
+    fn max_output_bytes_records_the_simplify_iterations() {
+        let rng = Rng::with_seed(7);
+        let noise: Vec<bool> = (0..48 * 48).map(|_| rng.bool()).collect();
+        let img = test_image(48, 48, |x, y| {
+            if noise[y * 48 + x] {
+                [0, 0, 0]
+            } else {
+                [255, 255, 255]
+            }
+        });
+        let config = config(Hierarchical::Stacked, PathSimplifyMode::Polygon);
+        let full = convert(img.clone(), config.clone()).unwrap();
+        assert_eq!(full.simplify_iterations, 0);
+        let max_bytes = full.to_bytes().len() / 2;
+        let config = Config {
+            max_output_bytes: Some(max_bytes),
+            ..config
+        };
+        let svg = convert(img, config).unwrap();
+        assert!(svg.simplify_iterations > 0);
+        assert!(svg.to_bytes().len() <= max_bytes);
+    }
+
+    #[test]
+    fn cutout_layers_do_not_overlap() {
+        let svg = convert(
+            nested_shapes(),
+            config(Hierarchical::Cutout, PathSimplifyMode::None),
+        )
+        .unwrap();
+        assert_eq!(svg.paths.len(), 4);
+        // Every pixel is covered exactly once, without overlaps or gaps
+        assert!(coverage(&svg).iter().all(|&count| count == 1));
+    }
+
+    #[test]
+    fn simplified_cutout_layers_only_differ_along_edges() {
+        let img = nested_shapes();
+        let svg = convert(
+            img.clone(),
+            config(Hierarchical::Cutout, PathSimplifyMode::Polygon),
+        )
+        .unwrap();
+        let on_edge = |x: usize, y: usize| {
+            let color = img.get_pixel(x, y);
+            [(0, 1), (2, 1), (1, 0), (1, 2)].iter().any(|&(dx, dy)| {
+                let (nx, ny) = ((x + dx).wrapping_sub(1), (y + dy).wrapping_sub(1));
+                nx < img.width && ny < img.height && img.get_pixel(nx, ny) != color
+            })
+        };
+        for (i, &count) in coverage(&svg).iter().enumerate() {
+            let (x, y) = (i % img.width, i / img.width);
+            assert!(
+                count == 1 || on_edge(x, y),
+                "{} paths at {},{}",
+                count,
+                x,
+                y
+            );
+        }
+    }
+
+    #[test]
+    fn stacked_layers_overlap() {
+        let svg = convert(
+            nested_shapes(),
+            config(Hierarchical::Stacked, PathSimplifyMode::None),
+        )
+        .unwrap();
+        assert!(coverage(&svg).iter().any(|&count| count > 1));
+    }
+
+    #[test]
+    fn layer_limit_merges_gradient_layers_without_gaps() {
+        // Eight bands, too far apart in color to cluster together
+        let img = test_image(64, 16, |x, _| {
+            let band = (x / 8) as u8 * 32;
+            [band, 0, 255 - band]
+        });
+        for hierarchical in [Hierarchical::Stacked, Hierarchical::Cutout] {
+            let unlimited = convert(
+                img.clone(),
+                config(hierarchical.clone(), PathSimplifyMode::None),
+            )
+            .unwrap();
+            assert!(unlimited.paths.len() > 3);
+            let config = Config {
+                layer_limit: Some(3),
+                ..config(hierarchical, PathSimplifyMode::None)
+            };
+            let svg = convert(img.clone(), config).unwrap();
+            assert!(svg.paths.len() <= 3, "{} paths", svg.paths.len());
+            assert!(coverage(&svg).iter().all(|&count| count > 0));
+        }
+    }
+
+    #[test]
+    fn zero_layer_limit_is_rejected() {
+        let config = Config {
+            layer_limit: Some(0),
+            ..Config::default()
+        };
+        assert!(convert(nested_shapes(), config).is_err());
+    }
 }
@@ -0,0 +1,159 @@
+use exif::{In, Tag};
+use image::{DynamicImage, ImageFormat};
+use png::PixelDimensions;
+use std::io::Cursor;
+use std::path::Path;
+
+/// JPEG XL bare codestream signature
+const JXL_CODESTREAM: &[u8] = &[0xff, 0x0a];
+/// JPEG XL ISOBMFF container signature
+const JXL_CONTAINER: &[u8] = &[
+    0x00, 0x00, 0x00, 0x0c, b'J', b'X', b'L', b' ', 0x0d, 0x0a, 0x87, 0x0a,
+];
+/// Photoshop document signature
+const PSD_SIGNATURE: &[u8] = b"8BPS";
+
+/// Decode an encoded image.
+/// The format is detected from the magic bytes first, and from the extension of `path`
+/// for files without a recognizable signature.
+pub(crate) fn decode_image(buffer: &[u8], path: Option<&Path>) -> Result<DynamicImage, String> {
+    let extension = path
+        .and_then(|path| path.extension())
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase());
+
+    if is_jxl(buffer) || extension.as_deref() == Some("jxl") {
+        return decode_jxl(buffer);
+    }
+    if is_avif(buffer) || extension.as_deref() == Some("avif") {
+        return decode_avif(buffer);
+    }
+    if buffer.starts_with(PSD_SIGNATURE) {
+        return decode_psd(buffer);
+    }
+
+    let format = match image::guess_format(buffer) {
+        Ok(format) => format,
+        Err(_) => match path.map(ImageFormat::from_path) {
+            Some(Ok(format)) => format,
+            _ => return Err(String::from("Input is not a supported image format")),
+        },
+    };
+    let img = image::load_from_memory_with_format(buffer, format)
+        .map_err(|_| String::from("Input is not a supported image format"))?;
+    Ok(apply_exif_orientation(img, buffer))
+}
+
+/// Rotate and flip `img` upright as given by the EXIF `Orientation` tag of `buffer`,
+/// the way image viewers display it. Images without the tag are returned as they are.
+fn apply_exif_orientation(img: DynamicImage, buffer: &[u8]) -> DynamicImage {
+    let orientation = exif::Reader::new()
+        .read_from_container(&mut Cursor::new(buffer))
+        .ok()
+        .and_then(|exif| {
+            exif.get_field(Tag::Orientation, In::PRIMARY)
+                .and_then(|field| field.value.get_uint(0))
+        });
+    match orientation {
+        Some(2) => img.fliph(),
+        Some(3) => img.rotate180(),
+        Some(4) => img.flipv(),
+        Some(5) => img.rotate90().fliph(),
+        Some(6) => img.rotate90(),
+        Some(7) => img.rotate270().fliph(),
+        Some(8) => img.rotate270(),
+        _ => img,
+    }
+}
+
+/// Resolution of a PNG image in dots per inch, from its `pHYs` chunk.
+/// Only the horizontal resolution is read, as non-square pixels are rare.
+pub(crate) fn png_dpi(buffer: &[u8]) -> Option<f64> {
+    if image::guess_format(buffer).ok()? != ImageFormat::Png {
+        return None;
+    }
+    let (_, reader) = png::Decoder::new(Cursor::new(buffer)).read_info().ok()?;
+    match reader.info().pixel_dims? {
+        PixelDimensions {
+            xppu,
+            unit: png::Unit::Meter,
+            ..
+        } if xppu > 0 => Some(xppu as f64 * 0.0254),
+        _ => None,
+    }
+}
+
+fn is_jxl(buffer: &[u8]) -> bool {
+    buffer.starts_with(JXL_CODESTREAM) || buffer.starts_with(JXL_CONTAINER)
+}
+
+/// An ISOBMFF `ftyp` box with the `avif` or `avis` (sequence) major brand
+fn is_avif(buffer: &[u8]) -> bool {
+    buffer.len() >= 12 && &buffer[4..8] == b"ftyp" && matches!(&buffer[8..12], b"avif" | b"avis")
+}
+
+#[cfg(feature = "jxl")]
+fn decode_jxl(buffer: &[u8]) -> Result<DynamicImage, String> {
+    use image::ImageBuffer;
+
+    let image = jxl_oxide::JxlImage::builder()
+        .read(buffer)
+        .map_err(|err| format!("Cannot decode JPEG XL image: {}", err))?;
+    let render = image
+        .render_frame(0)
+        .map_err(|err| format!("Cannot decode JPEG XL image: {}", err))?;
+    let mut stream = render.stream();
+    let (width, height) = (stream.width(), stream.height());
+    let mut samples = vec![0u8; (width * height * stream.channels()) as usize];
+    stream.write_to_buffer(&mut samples);
+
+    let img = match stream.channels() {
+        1 => ImageBuffer::from_raw(width, height, samples).map(DynamicImage::ImageLuma8),
+        2 => ImageBuffer::from_raw(width, height, samples).map(DynamicImage::ImageLumaA8),
+        3 => ImageBuffer::from_raw(width, height, samples).map(DynamicImage::ImageRgb8),
+        4 => ImageBuffer::from_raw(width, height, samples).map(DynamicImage::ImageRgba8),
+        _ => None,
+    };
+    img.ok_or_else(|| String::from("Unsupported JPEG XL channel layout"))
+}
+
+#[cfg(not(feature = "jxl"))]
+fn decode_jxl(_buffer: &[u8]) -> Result<DynamicImage, String> {
+    Err(String::from("JPEG XL input requires the `jxl` feature"))
+}
+
+#[cfg(feature = "avif")]
+fn decode_avif(buffer: &[u8]) -> Result<DynamicImage, String> {
+    let pixels =
+        libavif::decode_rgb(buffer).map_err(|err| format!("Cannot decode AVIF image: {}", err))?;
+    let img = image::RgbaImage::from_fn(pixels.width(), pixels.height(), |x, y| {
+        let (r, g, b, a) = pixels.pixel(x, y);
+        image::Rgba([r, g, b, a])
+    });
+    Ok(DynamicImage::ImageRgba8(img))
+}
+
+#[cfg(not(feature = "avif"))]
+fn decode_avif(_buffer: &[u8]) -> Result<DynamicImage, String> {
+    Err(String::from("AVIF input requires the `avif` feature"))
+}
+
+/// The visible layers of a Photoshop document merged into one image. Layer blend modes
+/// are not supported; every layer is composited as normal.
+#[cfg(feature = "psd")]
+fn decode_psd(buffer: &[u8]) -> Result<DynamicImage, String> {
+    let psd = psd::Psd::from_bytes(buffer)
+        .map_err(|err| format!("Cannot decode Photoshop document: {}", err))?;
+    // Hidden and fully transparent layers are always left out
+    let pixels = psd
+        .flatten_layers_rgba(&|_| true)
+        .map_err(|err| format!("Cannot merge the layers of the Photoshop document: {}", err))?;
+    image::RgbaImage::from_raw(psd.width(), psd.height(), pixels)
+        .map(DynamicImage::ImageRgba8)
+        .ok_or_else(|| String::from("Cannot merge the layers of the Photoshop document"))
+}
+
+#[cfg(not(feature = "psd"))]
+fn decode_psd(_buffer: &[u8]) -> Result<DynamicImage, String> {
+    Err(String::from("Photoshop input requires the `psd` feature"))
+}
@@ -0,0 +1,106 @@
+use std::collections::BTreeSet;
+
+use super::parse::{attribute, parse_translate, path_tags};
+
+/// Axis aligned bounds of the path points, control points included
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BoundingBox {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+}
+
+/// Semantic differences between two SVGs, as deltas from the first to the second
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DiffResult {
+    pub path_count_delta: isize,
+    /// Colors that only the second SVG paints with, as written in its attributes
+    pub added_colors: BTreeSet<String>,
+    /// Colors that only the first SVG paints with
+    pub removed_colors: BTreeSet<String>,
+    /// Edge by edge difference of the bounding boxes of all paths
+    pub bounds_delta: BoundingBox,
+    /// Difference in the total length of the `d` attributes, in bytes
+    pub path_data_length_delta: isize,
+}
+
+impl DiffResult {
+    /// Whether no metric differs
+    pub fn is_identical(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[derive(Default)]
+struct SvgSummary {
+    path_count: usize,
+    colors: BTreeSet<String>,
+    bounds: Option<BoundingBox>,
+    path_data_length: usize,
+}
+
+/// Compare the paths of two SVGs as written by `SvgFile`: path counts, colors, bounding
+/// boxes and path data length. Only absolute path commands and `translate` transforms
+/// are understood, and malformed markup is skipped rather than reported.
+pub fn diff_svg(svg_a: &str, svg_b: &str) -> DiffResult {
+    let (a, b) = (summarize(svg_a), summarize(svg_b));
+    let bounds_a = a.bounds.unwrap_or_default();
+    let bounds_b = b.bounds.unwrap_or_default();
+    DiffResult {
+        path_count_delta: b.path_count as isize - a.path_count as isize,
+        added_colors: b.colors.difference(&a.colors).cloned().collect(),
+        removed_colors: a.colors.difference(&b.colors).cloned().collect(),
+        bounds_delta: BoundingBox {
+            min_x: bounds_b.min_x - bounds_a.min_x,
+            min_y: bounds_b.min_y - bounds_a.min_y,
+            max_x: bounds_b.max_x - bounds_a.max_x,
+            max_y: bounds_b.max_y - bounds_a.max_y,
+        },
+        path_data_length_delta: b.path_data_length as isize - a.path_data_length as isize,
+    }
+}
+
+fn summarize(svg: &str) -> SvgSummary {
+    let mut summary = SvgSummary::default();
+    for (tag, color) in path_tags(svg) {
+        summary.path_count += 1;
+        if let Some(color) = color {
+            summary.colors.insert(color);
+        }
+        let data = attribute(tag, "d").unwrap_or_default();
+        summary.path_data_length += data.len();
+        let (dx, dy) = attribute(tag, "transform")
+            .and_then(parse_translate)
+            .unwrap_or_default();
+        for (x, y) in coordinates(data) {
+            extend(&mut summary.bounds, x + dx, y + dy);
+        }
+    }
+    summary
+}
+
+/// Coordinate pairs of path data made of absolute `M`, `L`, `C` and `Z` commands
+fn coordinates(data: &str) -> Vec<(f64, f64)> {
+    let numbers: Vec<f64> = data
+        .split(|c: char| c.is_ascii_alphabetic() || c == ',' || c.is_whitespace())
+        .filter_map(|s| s.parse().ok())
+        .collect();
+    numbers
+        .chunks_exact(2)
+        .map(|pair| (pair[0], pair[1]))
+        .collect()
+}
+
+fn extend(bounds: &mut Option<BoundingBox>, x: f64, y: f64) {
+    let bounds = bounds.get_or_insert(BoundingBox {
+        min_x: x,
+        min_y: y,
+        max_x: x,
+        max_y: y,
+    });
+    bounds.min_x = bounds.min_x.min(x);
+    bounds.min_y = bounds.min_y.min(y);
+    bounds.max_x = bounds.max_x.max(x);
+    bounds.max_y = bounds.max_y.max(y);
+}
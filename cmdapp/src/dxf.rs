@@ -0,0 +1,162 @@
+use std::fmt;
+use visioncortex::PointF64;
+
+use super::config::Rgba;
+use super::svg::SvgFile;
+use super::vector::{PathSegment, VectorPath};
+
+/// First entity handle; lower handles are conventionally reserved for tables and blocks
+const FIRST_HANDLE: usize = 0x100;
+
+/// DXF (R2010) rendering of the paths of an `SvgFile`.
+/// Polygons are written as LWPOLYLINE entities and splines as cubic SPLINE entities.
+/// The y axis is flipped, as DXF has its origin at the bottom left corner.
+pub struct DxfFile<'a> {
+    svg: &'a SvgFile,
+}
+
+impl<'a> DxfFile<'a> {
+    pub fn new(svg: &'a SvgFile) -> Self {
+        Self { svg }
+    }
+
+    fn flip(&self, point: PointF64) -> PointF64 {
+        PointF64::new(point.x, self.svg.height as f64 - point.y)
+    }
+}
+
+impl fmt::Display for DxfFile<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let paths = VectorPath::from_svg(self.svg);
+        let subpaths: Vec<(Rgba, &[PathSegment])> = paths
+            .iter()
+            .filter_map(|path| Some((path.fill.or(path.stroke)?, &path.segments)))
+            .flat_map(|(color, segments)| {
+                // One entity per subpath, each starting with a MoveTo
+                segments
+                    .chunk_by(|_, segment| !matches!(segment, PathSegment::MoveTo(_)))
+                    .map(move |subpath| (color, subpath))
+            })
+            .collect();
+
+        write_section(f, "HEADER")?;
+        write_pair(f, 9, "$ACADVER")?;
+        write_pair(f, 1, "AC1024")?;
+        write_pair(f, 9, "$HANDSEED")?;
+        write_pair(f, 5, format!("{:X}", FIRST_HANDLE + subpaths.len()))?;
+        write_pair(f, 0, "ENDSEC")?;
+
+        write_section(f, "ENTITIES")?;
+        for (i, (color, segments)) in subpaths.into_iter().enumerate() {
+            let handle = FIRST_HANDLE + i;
+            if segments
+                .iter()
+                .any(|segment| matches!(segment, PathSegment::CubicBezierTo { .. }))
+            {
+                self.write_spline(f, handle, color, segments)?;
+            } else {
+                self.write_polyline(f, handle, color, segments)?;
+            }
+        }
+        write_pair(f, 0, "ENDSEC")?;
+
+        write_pair(f, 0, "EOF")
+    }
+}
+
+impl DxfFile<'_> {
+    /// Closed subpaths are flagged as such rather than ending on their first point
+    fn write_polyline(
+        &self,
+        f: &mut fmt::Formatter,
+        handle: usize,
+        color: Rgba,
+        segments: &[PathSegment],
+    ) -> fmt::Result {
+        let points: Vec<PointF64> = segments
+            .iter()
+            .filter_map(|segment| match *segment {
+                PathSegment::MoveTo(point) | PathSegment::LineTo(point) => Some(point),
+                _ => None,
+            })
+            .collect();
+        let closed = segments.last() == Some(&PathSegment::ClosePath);
+
+        write_entity(f, "LWPOLYLINE", handle, color)?;
+        write_pair(f, 100, "AcDbPolyline")?;
+        write_pair(f, 90, points.len())?;
+        write_pair(f, 70, if closed { 1 } else { 0 })?;
+        for point in points {
+            let point = self.flip(point);
+            write_pair(f, 10, point.x)?;
+            write_pair(f, 20, point.y)?;
+        }
+        Ok(())
+    }
+
+    /// A spline made of `n` cubic bezier curves is a degree 3 B-spline with `3n + 1`
+    /// control points and every internal knot repeated 3 times
+    fn write_spline(
+        &self,
+        f: &mut fmt::Formatter,
+        handle: usize,
+        color: Rgba,
+        segments: &[PathSegment],
+    ) -> fmt::Result {
+        let mut points = vec![];
+        for segment in segments {
+            match *segment {
+                PathSegment::MoveTo(point) => points.push(point),
+                PathSegment::CubicBezierTo {
+                    control1,
+                    control2,
+                    end,
+                } => points.extend([control1, control2, end]),
+                PathSegment::LineTo(_) | PathSegment::ClosePath => {}
+            }
+        }
+        let num_curves = points.len().saturating_sub(1) / 3;
+        let mut knots = vec![0; 4];
+        for i in 1..num_curves {
+            knots.extend([i; 3]);
+        }
+        knots.extend([num_curves; 4]);
+
+        write_entity(f, "SPLINE", handle, color)?;
+        write_pair(f, 100, "AcDbSpline")?;
+        write_pair(f, 70, 8)?; // planar
+        write_pair(f, 71, 3)?;
+        write_pair(f, 72, knots.len())?;
+        write_pair(f, 73, points.len())?;
+        write_pair(f, 74, 0)?;
+        for knot in knots {
+            write_pair(f, 40, knot)?;
+        }
+        for point in points {
+            let point = self.flip(point);
+            write_pair(f, 10, point.x)?;
+            write_pair(f, 20, point.y)?;
+            write_pair(f, 30, 0.0)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_pair(f: &mut fmt::Formatter, code: u16, value: impl fmt::Display) -> fmt::Result {
+    writeln!(f, "{}", code)?;
+    writeln!(f, "{}", value)
+}
+
+fn write_section(f: &mut fmt::Formatter, name: &str) -> fmt::Result {
+    write_pair(f, 0, "SECTION")?;
+    write_pair(f, 2, name)
+}
+
+fn write_entity(f: &mut fmt::Formatter, kind: &str, handle: usize, color: Rgba) -> fmt::Result {
+    write_pair(f, 0, kind)?;
+    write_pair(f, 5, format!("{:X}", handle))?;
+    write_pair(f, 100, "AcDbEntity")?;
+    write_pair(f, 8, "0")?;
+    let true_color = ((color.r as u32) << 16) | ((color.g as u32) << 8) | color.b as u32;
+    write_pair(f, 420, true_color)
+}
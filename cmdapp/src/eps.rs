@@ -0,0 +1,115 @@
+use std::fmt;
+use visioncortex::PointF64;
+
+use super::svg::SvgFile;
+use super::vector::{number, PathSegment, VectorPath};
+
+/// Encapsulated PostScript (Level 2) rendering of the paths of an `SvgFile`.
+/// The bounding box is the image size in pixels, one pixel per PostScript point.
+/// The y axis is flipped, as PostScript has its origin at the bottom left corner.
+pub struct EpsFile<'a> {
+    svg: &'a SvgFile,
+}
+
+impl<'a> EpsFile<'a> {
+    pub fn new(svg: &'a SvgFile) -> Self {
+        Self { svg }
+    }
+
+    fn flip(&self, point: PointF64) -> PointF64 {
+        PointF64::new(point.x, self.svg.height as f64 - point.y)
+    }
+}
+
+impl fmt::Display for EpsFile<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (width, height) = (self.svg.width, self.svg.height);
+        writeln!(f, "%!PS-Adobe-3.0 EPSF-3.0")?;
+        writeln!(
+            f,
+            "%%Creator: visioncortex VTracer {}",
+            env!("CARGO_PKG_VERSION")
+        )?;
+        writeln!(f, "%%BoundingBox: 0 0 {} {}", width, height)?;
+        writeln!(f, "%%HiResBoundingBox: 0 0 {} {}", width, height)?;
+        writeln!(f, "%%LanguageLevel: 2")?;
+        writeln!(f, "%%Pages: 1")?;
+        writeln!(f, "%%EndComments")?;
+        writeln!(f, "%%Page: 1 1")?;
+        writeln!(f, "gsave")?;
+
+        // Transparency is not part of PostScript, so alpha is only honoured as on or off
+        if let Some(color) = self.svg.background_color.filter(|color| color.a > 0) {
+            write_color(f, color.r, color.g, color.b)?;
+            writeln!(
+                f,
+                "newpath 0 0 moveto {} 0 lineto {} {} lineto 0 {} lineto closepath fill",
+                width, width, height, height
+            )?;
+        }
+        if let Some(width) = self.svg.stroke_width {
+            writeln!(f, "{} setlinewidth", number(width.max(0.0)))?;
+        }
+        for path in VectorPath::from_svg(self.svg) {
+            if let Some(color) = path.fill.or(path.stroke) {
+                write_color(f, color.r, color.g, color.b)?;
+            }
+            writeln!(f, "newpath")?;
+            for segment in path.segments {
+                self.write_segment(f, segment)?;
+            }
+            if path.stroke.is_some() {
+                writeln!(f, "stroke")?;
+            } else {
+                writeln!(f, "fill")?;
+            }
+        }
+
+        writeln!(f, "grestore")?;
+        writeln!(f, "showpage")?;
+        writeln!(f, "%%EOF")
+    }
+}
+
+impl EpsFile<'_> {
+    fn write_segment(&self, f: &mut fmt::Formatter, segment: PathSegment) -> fmt::Result {
+        match segment {
+            PathSegment::MoveTo(point) => {
+                let point = self.flip(point);
+                writeln!(f, "{} {} moveto", number(point.x), number(point.y))
+            }
+            PathSegment::LineTo(point) => {
+                let point = self.flip(point);
+                writeln!(f, "{} {} lineto", number(point.x), number(point.y))
+            }
+            PathSegment::CubicBezierTo {
+                control1,
+                control2,
+                end,
+            } => {
+                let [a, b, c] = [control1, control2, end].map(|point| self.flip(point));
+                writeln!(
+                    f,
+                    "{} {} {} {} {} {} curveto",
+                    number(a.x),
+                    number(a.y),
+                    number(b.x),
+                    number(b.y),
+                    number(c.x),
+                    number(c.y)
+                )
+            }
+            PathSegment::ClosePath => writeln!(f, "closepath"),
+        }
+    }
+}
+
+fn write_color(f: &mut fmt::Formatter, r: u8, g: u8, b: u8) -> fmt::Result {
+    writeln!(
+        f,
+        "{} {} {} setrgbcolor",
+        number(r as f64 / 255.0),
+        number(g as f64 / 255.0),
+        number(b as f64 / 255.0)
+    )
+}
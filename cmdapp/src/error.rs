@@ -0,0 +1,43 @@
+use std::fmt;
+
+/// Error returned by the library API and the command line parser
+#[derive(Debug)]
+pub enum VTracerError {
+    /// A numeric parameter lies outside of its accepted range
+    OutOfRange {
+        param: &'static str,
+        value: f64,
+        min: f64,
+        max: f64,
+    },
+    /// A parameter could not be parsed
+    ParseError { param: &'static str, raw: String },
+    /// A config file could not be loaded or saved
+    ConfigFile(String),
+    /// The image could not be vectorized
+    Conversion(String),
+}
+
+impl fmt::Display for VTracerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::OutOfRange {
+                param,
+                value,
+                min,
+                max,
+            } => write!(
+                f,
+                "Out of Range Error: {} is invalid at {}. It must be within [{},{}].",
+                param, value, min, max
+            ),
+            Self::ParseError { param, raw } => {
+                write!(f, "Parser Error: {} is invalid: {}.", param, raw)
+            }
+            Self::ConfigFile(msg) => write!(f, "Config file error: {}", msg),
+            Self::Conversion(msg) => write!(f, "Conversion failed with error message: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for VTracerError {}
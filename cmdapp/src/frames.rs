@@ -0,0 +1,83 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use super::config::Config;
+use super::converter::{convert, write_svg};
+use image::codecs::gif::GifDecoder;
+use image::{AnimationDecoder, Frames};
+use visioncortex::ColorImage;
+
+/// Whether the file at `input_path` is a GIF with more than one frame
+pub fn is_animated_gif(input_path: &Path) -> bool {
+    match gif_frames(input_path) {
+        Ok(frames) => frames.take(2).count() > 1,
+        Err(_) => false,
+    }
+}
+
+/// Convert the frames of an animated GIF into a numbered series of files next to
+/// `output_path`: `out.svg` becomes `out_000.svg`, `out_001.svg`, and so on, numbered by
+/// frame index. Conversion starts at frame `start` and stops after `max_frames` frames if given.
+/// Every frame is converted with the same `config`.
+/// Returns the paths of the files written.
+pub fn convert_gif_frames(
+    input_path: &Path,
+    output_path: &Path,
+    config: Config,
+    start: usize,
+    max_frames: Option<usize>,
+) -> Result<Vec<PathBuf>, String> {
+    let format = config.output_format;
+    let title = input_path.file_name().map(|name| name.to_string_lossy());
+    let frames = gif_frames(input_path)?
+        .enumerate()
+        .skip(start)
+        .take(max_frames.unwrap_or(usize::MAX));
+
+    let mut written = vec![];
+    for (index, frame) in frames {
+        let frame = match frame {
+            Ok(frame) => frame.into_buffer(),
+            Err(_) => return Err(format!("Cannot decode frame {} of the GIF.", index)),
+        };
+        let (width, height) = (frame.width() as usize, frame.height() as usize);
+        let img = ColorImage {
+            pixels: frame.into_raw(),
+            width,
+            height,
+        };
+        log::debug!("Converting frame {}", index);
+        let svg = convert(img, config.clone())?;
+        let frame_path = numbered_path(output_path, index);
+        write_svg(svg, &frame_path, format, title.as_deref())?;
+        written.push(frame_path);
+    }
+    if written.is_empty() {
+        return Err(format!("The GIF has no frames from frame {} on.", start));
+    }
+    Ok(written)
+}
+
+fn gif_frames(input_path: &Path) -> Result<Frames<'static>, String> {
+    let file = match File::open(input_path) {
+        Ok(file) => file,
+        Err(_) => return Err(String::from("No image file found at specified input path")),
+    };
+    match GifDecoder::new(BufReader::new(file)) {
+        Ok(decoder) => Ok(decoder.into_frames()),
+        Err(_) => Err(String::from("Cannot decode the GIF.")),
+    }
+}
+
+fn numbered_path(output_path: &Path, index: usize) -> PathBuf {
+    let stem = output_path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let name = match output_path.extension() {
+        Some(extension) => format!("{}_{:03}.{}", stem, index, extension.to_string_lossy()),
+        None => format!("{}_{:03}", stem, index),
+    };
+    output_path.with_file_name(name)
+}
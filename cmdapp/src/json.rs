@@ -0,0 +1,122 @@
+#[cfg(feature = "serde")]
+use serde::Serialize;
+#[cfg(feature = "serde")]
+use serde_json::ser::{PrettyFormatter, Serializer};
+#[cfg(feature = "serde")]
+use visioncortex::{PointF64, ToSvgString};
+
+use super::svg::SvgFile;
+#[cfg(feature = "serde")]
+use super::vector::{hex_string, PathSegment, VectorPath};
+
+/// JSON rendering of the paths of an `SvgFile`, as an array of
+/// `{"fill":"#RRGGBB","d":"M... Z"}` objects in stacking order, with `stroke` in place of
+/// `fill` for outlines. The path data is absolute, in pixels of the traced image, without
+/// the margin, scale and translation of the SVG. Written on one line unless the `SvgFile`
+/// is indented. Requires the `serde` feature.
+pub struct JsonFile<'a> {
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    svg: &'a SvgFile,
+}
+
+impl<'a> JsonFile<'a> {
+    pub fn new(svg: &'a SvgFile) -> Self {
+        Self { svg }
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        let paths: Vec<JsonPath> = VectorPath::from_svg(self.svg)
+            .into_iter()
+            .map(|path| JsonPath {
+                fill: path.fill.map(hex_string),
+                stroke: path.stroke.map(hex_string),
+                d: path_data(&path.segments, self.svg.path_precision),
+            })
+            .collect();
+        let mut bytes = match self.svg.indent {
+            Some(indent) => {
+                let indent = " ".repeat(indent as usize);
+                let formatter = PrettyFormatter::with_indent(indent.as_bytes());
+                let mut bytes = vec![];
+                let mut serializer = Serializer::with_formatter(&mut bytes, formatter);
+                paths
+                    .serialize(&mut serializer)
+                    .map_err(|err| err.to_string())?;
+                bytes
+            }
+            None => serde_json::to_vec(&paths).map_err(|err| err.to_string())?,
+        };
+        bytes.push(b'\n');
+        Ok(bytes)
+    }
+
+    #[cfg(not(feature = "serde"))]
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        Err(String::from("JSON output requires the `serde` feature"))
+    }
+}
+
+/// One element of the JSON array, with either `fill` or `stroke`
+#[cfg(feature = "serde")]
+#[derive(Serialize)]
+struct JsonPath {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fill: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stroke: Option<String>,
+    d: String,
+}
+
+#[cfg(feature = "serde")]
+fn path_data(segments: &[PathSegment], precision: Option<u32>) -> String {
+    let point = |point: &PointF64| point.to_svg_string(precision);
+    let commands: Vec<String> = segments
+        .iter()
+        .map(|segment| match segment {
+            PathSegment::MoveTo(to) => format!("M{}", point(to)),
+            PathSegment::LineTo(to) => format!("L{}", point(to)),
+            PathSegment::CubicBezierTo {
+                control1,
+                control2,
+                end,
+            } => format!("C{} {} {}", point(control1), point(control2), point(end)),
+            PathSegment::ClosePath => String::from("Z"),
+        })
+        .collect();
+    commands.join(" ")
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+    use visioncortex::{Color, CompoundPath, PathF64};
+
+    fn triangle_svg(indent: Option<u32>) -> SvgFile {
+        let mut svg = SvgFile::new(4, 4, None);
+        svg.indent = indent;
+        let mut path = CompoundPath::new();
+        path.add_path_f64(PathF64::from_points(vec![
+            PointF64::new(0.0, 0.0),
+            PointF64::new(2.0, 0.0),
+            PointF64::new(0.0, 2.0),
+            PointF64::new(0.0, 0.0),
+        ]));
+        svg.add_path(path, Color::new(255, 0, 0));
+        svg
+    }
+
+    #[test]
+    fn compact_unless_indented() {
+        let compact = JsonFile::new(&triangle_svg(None)).to_bytes().unwrap();
+        assert_eq!(
+            String::from_utf8(compact).unwrap(),
+            "[{\"fill\":\"#FF0000\",\"d\":\"M0,0 L2,0 L0,2 Z\"}]\n"
+        );
+        let pretty = JsonFile::new(&triangle_svg(Some(2))).to_bytes().unwrap();
+        assert_eq!(
+            String::from_utf8(pretty).unwrap(),
+            "[\n  {\n    \"fill\": \"#FF0000\",\n    \"d\": \"M0,0 L2,0 L0,2 Z\"\n  }\n]\n"
+        );
+    }
+}
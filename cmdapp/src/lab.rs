@@ -0,0 +1,71 @@
+use super::palette::rgb_to_lab;
+use visioncortex::{Color, ColorImage};
+
+/// D65 reference white, in XYZ
+const WHITE: [f64; 3] = [0.95047, 1.0, 1.08883];
+
+/// Replace every pixel with its CIELAB color, keeping alpha untouched.
+/// L* in [0, 100] is stretched to [0, 255], and a* and b* are offset by 128.
+pub(crate) fn to_lab(mut img: ColorImage) -> ColorImage {
+    for pixel in img.pixels.chunks_exact_mut(4) {
+        let lab = rgb_to_lab([pixel[0], pixel[1], pixel[2]].map(f64::from));
+        let (l, a, b) = (lab.l, lab.a, lab.b);
+        pixel[0] = (l * 2.55).round().clamp(0.0, 255.0) as u8;
+        pixel[1] = (a + 128.0).round().clamp(0.0, 255.0) as u8;
+        pixel[2] = (b + 128.0).round().clamp(0.0, 255.0) as u8;
+    }
+    img
+}
+
+/// The sRGB color of a color encoded by `to_lab`, clamped to the sRGB gamut
+pub(crate) fn lab_to_color(color: Color) -> Color {
+    let lab = [
+        color.r as f64 / 2.55,
+        color.g as f64 - 128.0,
+        color.b as f64 - 128.0,
+    ];
+    let [r, g, b] = lab_to_rgb(lab);
+    Color::new_rgba(r, g, b, color.a)
+}
+
+/// Gamma encode the color channels of every pixel from linear light to sRGB
+pub(crate) fn linear_to_srgb(mut img: ColorImage) -> ColorImage {
+    let table: Vec<u8> = (0..=255)
+        .map(|v| (to_srgb(v as f64 / 255.0) * 255.0).round() as u8)
+        .collect();
+    for pixel in img.pixels.chunks_exact_mut(4) {
+        pixel[0] = table[pixel[0] as usize];
+        pixel[1] = table[pixel[1] as usize];
+        pixel[2] = table[pixel[2] as usize];
+    }
+    img
+}
+
+fn lab_to_rgb([l, a, b]: [f64; 3]) -> [u8; 3] {
+    let fy = (l + 16.0) / 116.0;
+    let f = [fy + a / 500.0, fy, fy - b / 200.0];
+    let [x, y, z] = [0, 1, 2].map(|i| lab_f_inverse(f[i]) * WHITE[i]);
+    [
+        3.2406 * x - 1.5372 * y - 0.4986 * z,
+        -0.9689 * x + 1.8758 * y + 0.0415 * z,
+        0.0557 * x - 0.2040 * y + 1.0570 * z,
+    ]
+    .map(|c| (to_srgb(c.clamp(0.0, 1.0)) * 255.0).round() as u8)
+}
+
+fn to_srgb(c: f64) -> f64 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn lab_f_inverse(t: f64) -> f64 {
+    const DELTA: f64 = 6.0 / 29.0;
+    if t > DELTA {
+        t * t * t
+    } else {
+        3.0 * DELTA * DELTA * (t - 4.0 / 29.0)
+    }
+}
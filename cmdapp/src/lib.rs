@@ -8,15 +8,67 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+mod batch;
+mod benchmark;
+mod bezier;
+mod builder;
+mod canny;
+mod canvas;
+mod complexity;
 mod config;
 mod converter;
+mod decoder;
+mod diff;
+mod dxf;
+mod eps;
+mod error;
+mod frames;
+mod json;
+mod lab;
+mod palette;
+mod parse;
+mod pdf;
+mod progress;
 #[cfg(feature = "python-binding")]
 mod python;
+#[cfg(feature = "render")]
+mod render;
+mod rust;
+mod stats;
 mod svg;
+mod trace;
+mod validate;
+mod vector;
+#[cfg(feature = "wasm")]
+mod wasm;
 
+pub use batch::*;
+pub use benchmark::*;
+pub use builder::*;
+pub use canvas::*;
+pub use complexity::*;
 pub use config::*;
 pub use converter::*;
+pub use diff::*;
+pub use dxf::*;
+pub use eps::*;
+pub use error::*;
+pub use frames::*;
+pub use image::DynamicImage;
+pub use json::*;
+pub use parse::*;
+pub use pdf::*;
+pub use progress::*;
 #[cfg(feature = "python-binding")]
 pub use python::*;
+#[cfg(feature = "render")]
+pub use render::*;
+pub use rust::*;
+pub use stats::*;
 pub use svg::*;
-pub use visioncortex::ColorImage;
+pub use trace::*;
+pub use validate::*;
+pub use vector::*;
+pub use visioncortex::{ColorImage, PointF64};
+#[cfg(feature = "wasm")]
+pub use wasm::*;
@@ -1,23 +1,136 @@
-mod config;
-mod converter;
-mod svg;
-
 use clap::{App, Arg};
-use config::{ColorMode, Config, Hierarchical, Preset};
-use std::path::PathBuf;
+use log::LevelFilter;
+use rayon::ThreadPoolBuilder;
+use std::cell::Cell;
+use std::fs::File;
+use std::io::{self, Cursor, Read, Write};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
-use visioncortex::PathSimplifyMode;
+use std::time::{Duration, Instant};
+use vtracer::{
+    is_animated_gif, ColorMode, Config, ConverterConfig, Hierarchical, OutputFormat,
+    PathSimplifyMode, Preset, ProgressReporter, Severity, Stage, StrokeMode, VTracerError,
+};
+
+/// Parsed command line arguments
+pub struct Args {
+    pub input_path: PathBuf,
+    pub output_path: PathBuf,
+    pub config: Config,
+    /// Treat input and output paths as directories
+    pub batch: bool,
+    /// Descend into subdirectories in batch mode
+    pub recursive: bool,
+    /// Draw a progress bar on stderr
+    pub progress: bool,
+    /// Print the time taken by every stage on stderr
+    pub profile: bool,
+    /// First frame to convert of an animated GIF
+    pub gif_start: usize,
+    /// Maximum number of frames to convert of an animated GIF
+    pub gif_frames: Option<usize>,
+    /// Most verbose level of the messages written to stderr
+    pub log_level: LevelFilter,
+    /// Time allowed to download an input given as a URL
+    pub timeout: Option<Duration>,
+    /// Convert again whenever the input file changes
+    pub watch: bool,
+}
+
+/// Prints the time taken by every stage on stderr as it completes, measured from the end of
+/// the previous one
+struct StageTimer {
+    last: Cell<Instant>,
+    /// Also draws the progress bar
+    progress: Option<StderrProgress>,
+}
+
+impl StageTimer {
+    fn new(progress: bool) -> Self {
+        Self {
+            last: Cell::new(Instant::now()),
+            progress: progress.then(StderrProgress::default),
+        }
+    }
+}
 
-fn path_simplify_mode_from_str(s: &str) -> PathSimplifyMode {
-    match s {
-        "polygon" => PathSimplifyMode::Polygon,
-        "spline" => PathSimplifyMode::Spline,
-        "none" => PathSimplifyMode::None,
-        _ => panic!("unknown PathSimplifyMode {}", s),
+impl ProgressReporter for StageTimer {
+    fn on_progress(&self, stage: Stage, fraction: f32) {
+        if let Some(progress) = &self.progress {
+            progress.on_progress(stage, fraction);
+        }
+        if fraction >= 1.0 {
+            let now = Instant::now();
+            let elapsed = now - self.last.replace(now);
+            eprintln!("{:<21}{:>6}ms", format!("{}:", stage), elapsed.as_millis());
+        }
     }
 }
 
-pub fn config_from_args() -> (PathBuf, PathBuf, Config) {
+/// Passed as input or output path to read from stdin or write to stdout
+const STDIO_PATH: &str = "-";
+
+/// Draws a single line progress bar on stderr
+#[derive(Default)]
+struct StderrProgress {
+    /// Last drawn stage and percentage, to skip redundant redraws
+    last: Cell<Option<(Stage, u32)>>,
+}
+
+impl ProgressReporter for StderrProgress {
+    fn on_progress(&self, stage: Stage, fraction: f32) {
+        const WIDTH: usize = 30;
+        let percent = (fraction.clamp(0.0, 1.0) * 100.0) as u32;
+        if self.last.replace(Some((stage, percent))) == Some((stage, percent)) {
+            return;
+        }
+        let filled = WIDTH * percent as usize / 100;
+        eprint!(
+            "\r{:<14} [{}{}] {:>3}%",
+            format!("{:?}", stage),
+            "#".repeat(filled),
+            " ".repeat(WIDTH - filled),
+            percent
+        );
+        if fraction >= 1.0 {
+            eprintln!();
+        }
+    }
+}
+
+/// Parse a `--mode` value; `pixel` is the CLI name of `PathSimplifyMode::None`
+fn path_simplify_mode_from_str(s: &str) -> Result<PathSimplifyMode, String> {
+    PathSimplifyMode::from_str(s)
+}
+
+/// Parse the raw value of `param`
+fn parse_arg<T: FromStr>(param: &'static str, raw: &str) -> Result<T, VTracerError> {
+    raw.trim().parse().map_err(|_| VTracerError::ParseError {
+        param,
+        raw: raw.to_owned(),
+    })
+}
+
+/// Parse the raw value of `param` and check that it lies within `[min, max]`
+fn parse_arg_in_range<T>(param: &'static str, raw: &str, min: T, max: T) -> Result<T, VTracerError>
+where
+    T: FromStr + PartialOrd + Copy,
+    f64: From<T>,
+{
+    let value: T = parse_arg(param, raw)?;
+    // Written so that NaN is out of range too, and infinities are whatever the bounds
+    if !(f64::from(value).is_finite() && value >= min && value <= max) {
+        return Err(VTracerError::OutOfRange {
+            param,
+            value: value.into(),
+            min: min.into(),
+            max: max.into(),
+        });
+    }
+    Ok(value)
+}
+
+pub fn config_from_args() -> Result<Args, VTracerError> {
     let app = App::new("visioncortex VTracer ".to_owned() + env!("CARGO_PKG_VERSION"))
         .about("A cmd app to convert images into vector graphics.");
 
@@ -26,24 +139,112 @@ pub fn config_from_args() -> (PathBuf, PathBuf, Config) {
             .long("input")
             .short("i")
             .takes_value(true)
-            .help("Path to input raster image")
+            .help("Path to input raster image, `-` to read from stdin, or an HTTP(S) URL")
             .required(true),
     );
 
+    let app = app.arg(
+        Arg::with_name("timeout")
+            .long("timeout")
+            .takes_value(true)
+            .help("Seconds allowed to download an input URL"),
+    );
+
     let app = app.arg(
         Arg::with_name("output")
             .long("output")
             .short("o")
             .takes_value(true)
-            .help("Path to output vector graphics")
+            .help("Path to output vector graphics, or `-` to write to stdout")
             .required(true),
     );
 
+    let app = app.arg(Arg::with_name("batch").long("batch").help(
+        "Convert every supported image in the input directory into the output directory. \
+            Implied when the input path is a directory.",
+    ));
+
+    let app = app.arg(
+        Arg::with_name("recursive")
+            .long("recursive")
+            .help("Also convert images in subdirectories in batch mode"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("quiet")
+            .long("quiet")
+            .short("q")
+            .conflicts_with("verbose")
+            .help("Only print errors"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("verbose")
+            .long("verbose")
+            .short("v")
+            .help("Print debugging details of the conversion"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("progress")
+            .long("progress")
+            .help("Show conversion progress on stderr"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("profile")
+            .long("profile")
+            .help("Print the time taken by every stage of the conversion on stderr"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("watch")
+            .long("watch")
+            .conflicts_with("batch")
+            .help("Convert again whenever the input file changes, until interrupted"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("parallel")
+            .long("parallel")
+            .help("Build the paths of color layers on multiple threads"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("cpu_threads")
+            .long("cpu-threads")
+            .takes_value(true)
+            .help("Number of threads used by --parallel, instead of one per CPU"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("seed")
+            .long("seed")
+            .takes_value(true)
+            .help("Seed of the random key color for transparent pixels, for reproducible output"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("gif_frames")
+            .long("gif-frames")
+            .takes_value(true)
+            .help("Convert at most N frames of an animated GIF"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("gif_start")
+            .long("gif-start")
+            .takes_value(true)
+            .help("First frame to convert of an animated GIF"),
+    );
+
     let app = app.arg(
         Arg::with_name("color_mode")
             .long("colormode")
             .takes_value(true)
-            .help("True color image `color` (default) or Binary image `bw`"),
+            .help(
+                "True color image `color` (default), Binary image `bw` or Grayscale image `gray`",
+            ),
     );
 
     let app = app.arg(
@@ -60,7 +261,46 @@ pub fn config_from_args() -> (PathBuf, PathBuf, Config) {
         Arg::with_name("preset")
             .long("preset")
             .takes_value(true)
-            .help("Use one of the preset configs `bw`, `poster`, `photo`"),
+            .help(
+                "Use one of the preset configs `bw`, `poster`, `photo`, `line`, or `custom`, \
+                or `auto` to choose between the first three from the image",
+            ),
+    );
+
+    let app = app.arg(
+        Arg::with_name("preset_file")
+            .long("preset-file")
+            .takes_value(true)
+            .required_if("preset", "custom")
+            .help("Path to the TOML config file used by `--preset custom`"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("load_config")
+            .long("load-config")
+            .takes_value(true)
+            .help("Load a config file previously written by --save-config"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("save_config")
+            .long("save-config")
+            .takes_value(true)
+            .help("Save the effective config as a TOML file"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("format")
+            .long("format")
+            .alias("output-format")
+            .takes_value(true)
+            .help("Output file format `svg` (default), `svgz` (gzip compressed svg), `dxf`, `pdf`, `eps`, `png-trace` (outlines over the input, for tuning), `canvas` (html page drawing the paths with JavaScript), `json` (array of fill colors and path data, indented with --output-indent) or `rust` (source of the paths as constants for the vtracer-runtime crate). Output files ending in .svgz are compressed without it"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("split_by_color")
+            .long("split-by-color")
+            .help("Write the paths of each color to a file of their own, named OUTPUT_color_RRGGBB.svg after the output path, e.g. to cut each color from its own sheet. Pairs well with --hierarchical cutout"),
     );
 
     let app = app.arg(
@@ -68,7 +308,16 @@ pub fn config_from_args() -> (PathBuf, PathBuf, Config) {
             .long("filter_speckle")
             .short("f")
             .takes_value(true)
-            .help("Discard patches smaller than X px in size"),
+            .help(
+                "Discard patches smaller than X px in size, i.e. of an area under X squared pixels",
+            ),
+    );
+
+    let app = app.arg(
+        Arg::with_name("min_area")
+            .long("min-area")
+            .takes_value(true)
+            .help("Discard patches of fewer than X pixels. Unlike filter_speckle, X is not squared. Takes precedence over filter_speckle"),
     );
 
     let app = app.arg(
@@ -87,6 +336,22 @@ pub fn config_from_args() -> (PathBuf, PathBuf, Config) {
             .help("Color difference between gradient layers"),
     );
 
+    let app = app.arg(
+        Arg::with_name("input_colorspace")
+            .long("input-colorspace")
+            .takes_value(true)
+            .help("Encoding of the input pixels, `srgb` (default) or `linear` for linear light images"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("color_space")
+            .long("color-space")
+            .takes_value(true)
+            .help(
+                "Cluster colors in `rgb` or `lab` (CIELAB) space. Lab separates dark hues better",
+            ),
+    );
+
     let app = app.arg(
         Arg::with_name("corner_threshold")
             .long("corner_threshold")
@@ -109,14 +374,328 @@ pub fn config_from_args() -> (PathBuf, PathBuf, Config) {
             .help("Minimum angle displacement (degree) to splice a spline"),
     );
 
+    let app = app.arg(
+        Arg::with_name("max_iterations")
+            .long("max-iterations")
+            .alias("max_iterations")
+            .takes_value(true)
+            .help("Maximum number of smoothing iterations in [1, 100] [default: 10]. Higher values fit curves more accurately, at a linear cost in time"),
+    );
+
     let app = app.arg(
         Arg::with_name("mode")
             .long("mode")
             .short("m")
             .takes_value(true)
-            .help("Curver fitting mode `pixel`, `polygon`, `spline`"),
+            .help("Curver fitting mode `pixel`, `polygon`, `spline`, `bezier`"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("bezier_tolerance")
+            .long("bezier-tolerance")
+            .takes_value(true)
+            .help("Maximum deviation in px of the fitted curves in `bezier` mode"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("corner_rounding")
+            .long("corner-rounding")
+            .takes_value(true)
+            .help("Round every corner in `polygon` mode with an arc of this radius [0, 100]"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("max_colors")
+            .long("max-colors")
+            .takes_value(true)
+            .help("Merge the closest colors until at most N distinct fill colors remain [2, 256]"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("min_path_length")
+            .long("min-path-length")
+            .takes_value(true)
+            .help("Discard paths shorter than this total length in px"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("path_join_tolerance")
+            .long("path-join-tolerance")
+            .takes_value(true)
+            .help("Join open paths of one color whose ends are at most this many px apart [0, 10]. Defaults to 0.5"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("stroke_width")
+            .long("stroke-width")
+            .takes_value(true)
+            .help("Write paths as outlines of this width instead of filled regions. Only applies to binary mode."),
+    );
+
+    let app = app.arg(
+        Arg::with_name("background_color")
+            .long("background-color")
+            .takes_value(true)
+            .help("Paint a background of color `RRGGBB` or `RRGGBBAA` behind the paths"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("color_palette")
+            .long("color-palette")
+            .takes_value(true)
+            .help("Snap every color to the closest one of a palette: a GIMP .gpl file, or one #RRGGBB color per line"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("layer_limit")
+            .long("layer-limit")
+            .takes_value(true)
+            .help("Keep at most N color layers, merging the smallest into their neighbours"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("max_paths_per_cluster")
+            .long("max-paths-per-cluster")
+            .alias("max-colors-per-layer")
+            .takes_value(true)
+            .help("Keep at most N paths of each color, dropping the smallest"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("anti_alias")
+            .long("anti-alias")
+            .help("Snap anti-aliased edge pixels to the colors they blend before clustering"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("invert")
+            .long("invert")
+            .help("Invert the colors of the image before conversion, e.g. for light drawings on a dark background"),
+    );
+
+    let app = app.arg(Arg::with_name("ignore_alpha").long("ignore-alpha").help(
+        "Composite the image over white before conversion, tracing transparent areas as white",
+    ));
+
+    let app = app.arg(
+        Arg::with_name("threshold")
+            .long("threshold")
+            .takes_value(true)
+            .help("Luminance 0..255 below which pixels become black in binary mode [128], or `otsu` to pick it automatically"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("alpha_threshold")
+            .long("alpha-threshold")
+            .takes_value(true)
+            .help("Make pixels of alpha below A in [0, 255] fully transparent and leave them out of the paths"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("dither")
+            .long("dither")
+            .help("Dither the luminance before thresholding in binary mode (Floyd-Steinberg), for a 1-bit print look on gradients"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("canny")
+            .long("canny")
+            .help("Trace the edges found by the Canny edge detector in binary mode, instead of thresholding. Cleaner than thresholding on photographs"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("canny_low_threshold")
+            .long("canny-low-threshold")
+            .takes_value(true)
+            .help("Gradient in [0, high threshold] above which pixels next to an edge are part of it, with --canny [default: 10]"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("canny_high_threshold")
+            .long("canny-high-threshold")
+            .takes_value(true)
+            .help("Gradient in [0, 255] above which pixels are edges, with --canny [default: 30]. Gradients are in luminance steps between neighbouring pixels"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("grayscale_gamma")
+            .long("grayscale-gamma")
+            .takes_value(true)
+            .help("Gamma in [0.1, 3.0] applied to the luminance in grayscale and binary modes. Below 1 brightens shadows, above 1 darkens them"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("grayscale_weights")
+            .long("grayscale-weights")
+            .takes_value(true)
+            .help("Weights R,G,B of the luminance in grayscale and binary modes, summing to 1. Defaults to Rec. 601 `0.299,0.587,0.114`; `0.2126,0.7152,0.0722` is Rec. 709"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("resize_to")
+            .long("resize-to")
+            .takes_value(true)
+            .help("Downscale the image to fit within `WxH` pixels, keeping its aspect ratio, before anything else"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("region")
+            .long("region")
+            .takes_value(true)
+            .help(
+            "Convert only the rectangle `X,Y,W,H` of the image, in pixels from the top left corner",
+        ),
+    );
+
+    let app = app.arg(
+        Arg::with_name("aspect_ratio")
+            .long("aspect-ratio")
+            .takes_value(true)
+            .help("Pad or crop the image to the aspect ratio `W:H` before conversion, e.g. 16:9"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("aspect_ratio_fit")
+            .long("aspect-ratio-fit")
+            .takes_value(true)
+            .help("How to reach --aspect-ratio: [pad] with the background color or transparency, or crop around the center"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("opacity")
+            .long("opacity")
+            .takes_value(true)
+            .help("Opacity of the whole drawing in [0, 1], for overlays and watermarks"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("blend_mode")
+            .long("blend-mode")
+            .takes_value(true)
+            .help("CSS mix-blend-mode of every layer: `normal`, `multiply`, `screen` or `overlay`"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("viewbox_margin")
+            .long("viewbox-margin")
+            .takes_value(true)
+            .help("Padding in px added around the SVG viewBox on all four sides"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("translate")
+            .long("translate")
+            .takes_value(true)
+            .help("Shift the paths by X,Y user units, keeping the viewBox in place"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("clip_to_bounds")
+            .long("clip-to-bounds")
+            .help(
+                "Clip the paths to the image rectangle, hiding slivers of curves that overshoot it",
+            ),
+    );
+
+    let app = app.arg(
+        Arg::with_name("scale")
+            .long("scale")
+            .takes_value(true)
+            .help("Multiply the SVG dimensions and path coordinates by this factor, e.g. 3.7795275591 to turn pixels into mm at 96 dpi"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("unit")
+            .long("unit")
+            .takes_value(true)
+            .help("Write the SVG width and height in `px`, `mm`, `cm` or `in` at 96 dpi, or the resolution of a PNG input, with a viewBox in pixels"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("no_preserve_dpi")
+            .long("no-preserve-dpi")
+            .help("Ignore the resolution of PNG inputs. By default the SVG gets their physical size, in mm unless --unit is given"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("encoding")
+            .long("encoding")
+            .takes_value(true)
+            .help(
+                "Character encoding of the SVG, `utf8` (default) or `latin1` for legacy consumers",
+            ),
+    );
+
+    let app = app.arg(
+        Arg::with_name("output_indent")
+            .long("output-indent")
+            .takes_value(true)
+            .help("Write every SVG element, or JSON value, on its own line, indented by N spaces per level"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("max_file_size")
+            .long("max-file-size")
+            .takes_value(true)
+            .help("Largest SVG to write, in bytes. Larger results are traced again with filter_speckle raised by 1 until they fit, e.g. for data URLs or email"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("layer_names")
+            .long("layer-names")
+            .help("Put every path in a named layer for Inkscape and Figma"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("id_prefix")
+            .long("id-prefix")
+            .takes_value(true)
+            .help("Prefix of the ids of SVG elements, `vt` by default as in vt-layer-1, so SVGs embedded in one HTML page do not clash. Letters, digits, - and _, or empty for none"),
     );
 
+    let app = app.arg(
+        Arg::with_name("embed_original")
+            .long("embed-original")
+            .help("Embed the input image as a hidden base64 PNG below the paths"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("svg_title")
+            .long("svg-title")
+            .takes_value(true)
+            .help("Title of the SVG for screen readers, written as its <title>"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("svg_desc")
+            .long("svg-desc")
+            .takes_value(true)
+            .help("Description of the SVG for screen readers, written as its <desc>"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("optimize_svg")
+            .long("optimize-svg")
+            .help("Merge paths of the same color and deduplicate attributes in the output"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("dedupe_paths")
+            .long("dedupe-paths")
+            .help("Write paths of identical shape once as a <symbol>, placed with <use>. Shrinks patterned images"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("sort_paths_by")
+            .long("sort-paths-by")
+            .takes_value(true)
+            .help("Order of the paths within each layer: `area` (largest first, default), `color` (by hue) or `none` (as traced)"),
+    );
+
+    let app = app.arg(Arg::with_name("no_close_path").long("no-close-path").help(
+        "In binary mode, leave subpaths whose ends do not meet open, without Z. For pen plotters",
+    ));
+
     let app = app.arg(
         Arg::with_name("path_precision")
             .long("path_precision")
@@ -139,144 +718,739 @@ pub fn config_from_args() -> (PathBuf, PathBuf, Config) {
     let output_path = PathBuf::from(output_path);
 
     if let Some(value) = matches.value_of("preset") {
-        config = Config::from_preset(Preset::from_str(value).unwrap());
+        let preset = if value.trim() == "custom" {
+            let path = matches.value_of("preset_file").unwrap_or_default();
+            Preset::Custom(Box::new(Config::load_toml(path)?))
+        } else if value.trim() == "auto" {
+            // Directories, URLs and stdin would have to be read twice
+            if !input_path.is_file() {
+                return Err(VTracerError::ParseError {
+                    param: "preset",
+                    raw: String::from("auto, which needs an input file"),
+                });
+            }
+            vtracer::select_preset_for_file(&input_path).map_err(VTracerError::Conversion)?
+        } else {
+            Preset::from_str(value.trim()).map_err(|_| VTracerError::ParseError {
+                param: "preset",
+                raw: value.to_owned(),
+            })?
+        };
+        config = Config::from_preset(preset);
+    }
+
+    if let Some(value) = matches.value_of("load_config") {
+        config = Config::load_toml(value)?;
     }
 
     if let Some(value) = matches.value_of("color_mode") {
-        config.color_mode = ColorMode::from_str(if value.trim() == "bw" || value.trim() == "BW" {
-            "binary"
-        } else {
-            "color"
-        })
-        .unwrap()
+        let name = match value.trim() {
+            "bw" | "BW" => "binary",
+            name => name,
+        };
+        config.color_mode = ColorMode::from_str(name).map_err(|_| VTracerError::ParseError {
+            param: "color_mode",
+            raw: value.to_owned(),
+        })?;
     }
 
     if let Some(value) = matches.value_of("hierarchical") {
-        config.hierarchical = Hierarchical::from_str(value).unwrap()
+        config.hierarchical =
+            Hierarchical::from_str(value.trim()).map_err(|_| VTracerError::ParseError {
+                param: "hierarchical",
+                raw: value.to_owned(),
+            })?;
     }
 
     if let Some(value) = matches.value_of("mode") {
-        let value = value.trim();
-        config.mode = path_simplify_mode_from_str(if value == "pixel" {
-            "none"
-        } else if value == "polygon" {
-            "polygon"
-        } else if value == "spline" {
-            "spline"
-        } else {
-            panic!("Parser Error: Curve fitting mode is invalid: {}", value);
-        });
+        config.mode =
+            path_simplify_mode_from_str(value.trim()).map_err(|_| VTracerError::ParseError {
+                param: "mode",
+                raw: value.to_owned(),
+            })?;
     }
 
-    if let Some(value) = matches.value_of("filter_speckle") {
-        if value.trim().parse::<usize>().is_ok() {
-            // is numeric
-            let value = value.trim().parse::<usize>().unwrap();
-            if value > 16 {
-                panic!("Out of Range Error: Filter speckle is invalid at {}. It must be within [0,16].", value);
-            }
-            config.filter_speckle = value;
-        } else {
-            panic!(
-                "Parser Error: Filter speckle is not a positive integer: {}.",
-                value
-            );
+    if let Some(value) = matches.value_of("format") {
+        config.output_format =
+            OutputFormat::from_str(value.trim()).map_err(|_| VTracerError::ParseError {
+                param: "format",
+                raw: value.to_owned(),
+            })?;
+    } else if is_svgz(&output_path) {
+        config.output_format = OutputFormat::SvgCompressed;
+    }
+
+    if matches.is_present("split_by_color") {
+        if is_stdio(&input_path) || is_stdio(&output_path) || is_url(&input_path) {
+            return Err(VTracerError::ParseError {
+                param: "split_by_color",
+                raw: String::from("given with a stream or URL, which has no file name"),
+            });
         }
+        config.split_by_color = true;
+    }
+
+    if let Some(value) = matches.value_of("filter_speckle") {
+        config.filter_speckle = parse_arg_in_range::<u32>("filter_speckle", value, 0, 16)? as usize;
+    }
+
+    if let Some(value) = matches.value_of("min_area") {
+        let area = parse_arg_in_range("min_area", value, 0.0, f64::INFINITY)?;
+        config.filter_speckle_area_direct = Some(area);
     }
 
     if let Some(value) = matches.value_of("color_precision") {
-        if value.trim().parse::<i32>().is_ok() {
-            // is numeric
-            let value = value.trim().parse::<i32>().unwrap();
-            if value < 1 || value > 8 {
-                panic!("Out of Range Error: Color precision is invalid at {}. It must be within [1,8].", value);
-            }
-            config.color_precision = value;
-        } else {
-            panic!(
-                "Parser Error: Color precision is not an integer: {}.",
-                value
-            );
-        }
+        config.color_precision = parse_arg_in_range("color_precision", value, 1, 8)?;
     }
 
     if let Some(value) = matches.value_of("gradient_step") {
-        if value.trim().parse::<i32>().is_ok() {
-            // is numeric
-            let value = value.trim().parse::<i32>().unwrap();
-            if value < 0 || value > 255 {
-                panic!("Out of Range Error: Gradient step is invalid at {}. It must be within [0,255].", value);
-            }
-            config.layer_difference = value;
-        } else {
-            panic!("Parser Error: Gradient step is not an integer: {}.", value);
-        }
+        config.layer_difference = parse_arg_in_range("gradient_step", value, 0, 255)?;
+    }
+
+    if let Some(value) = matches.value_of("input_colorspace") {
+        config.input_colorspace = parse_arg("input_colorspace", value)?;
+    }
+
+    if let Some(value) = matches.value_of("color_space") {
+        config.color_space = parse_arg("color_space", value)?;
     }
 
     if let Some(value) = matches.value_of("corner_threshold") {
-        if value.trim().parse::<i32>().is_ok() {
-            // is numeric
-            let value = value.trim().parse::<i32>().unwrap();
-            if value < 0 || value > 180 {
-                panic!("Out of Range Error: Corner threshold is invalid at {}. It must be within [0,180].", value);
-            }
-            config.corner_threshold = value
-        } else {
-            panic!("Parser Error: Corner threshold is not numeric: {}.", value);
-        }
+        config.corner_threshold = parse_arg_in_range("corner_threshold", value, 0, 180)?;
     }
 
     if let Some(value) = matches.value_of("segment_length") {
-        if value.trim().parse::<f64>().is_ok() {
-            // is numeric
-            let value = value.trim().parse::<f64>().unwrap();
-            if value < 3.5 || value > 10.0 {
-                panic!("Out of Range Error: Segment length is invalid at {}. It must be within [3.5,10].", value);
-            }
-            config.length_threshold = value;
-        } else {
-            panic!("Parser Error: Segment length is not numeric: {}.", value);
-        }
+        config.length_threshold = parse_arg_in_range("segment_length", value, 3.5, 10.0)?;
     }
 
     if let Some(value) = matches.value_of("splice_threshold") {
-        if value.trim().parse::<i32>().is_ok() {
-            // is numeric
-            let value = value.trim().parse::<i32>().unwrap();
-            if value < 0 || value > 180 {
-                panic!("Out of Range Error: Segment length is invalid at {}. It must be within [0,180].", value);
-            }
-            config.splice_threshold = value;
-        } else {
-            panic!("Parser Error: Segment length is not numeric: {}.", value);
-        }
+        config.splice_threshold = parse_arg_in_range("splice_threshold", value, 0, 180)?;
+    }
+
+    if let Some(value) = matches.value_of("max_iterations") {
+        config.max_iterations =
+            parse_arg_in_range::<u32>("max_iterations", value, 1, 100)? as usize;
     }
 
     if let Some(value) = matches.value_of("path_precision") {
-        if value.trim().parse::<u32>().is_ok() {
-            // is numeric
-            let value = value.trim().parse::<u32>().ok();
-            config.path_precision = value;
+        config.path_precision = Some(parse_arg("path_precision", value)?);
+    }
+
+    if let Some(value) = matches.value_of("bezier_tolerance") {
+        config.bezier_tolerance = parse_arg_in_range("bezier_tolerance", value, 0.01, 10.0)?;
+    }
+
+    if let Some(value) = matches.value_of("corner_rounding") {
+        config.corner_rounding = parse_arg_in_range("corner_rounding", value, 0.0, 100.0)?;
+    }
+
+    if let Some(value) = matches.value_of("max_colors") {
+        config.max_colors = Some(parse_arg_in_range::<u32>("max_colors", value, 2, 256)? as usize);
+    }
+
+    if let Some(value) = matches.value_of("min_path_length") {
+        config.min_path_length = Some(parse_arg_in_range(
+            "min_path_length",
+            value,
+            0.0,
+            f64::INFINITY,
+        )?);
+    }
+
+    if let Some(value) = matches.value_of("path_join_tolerance") {
+        config.path_join_tolerance = parse_arg_in_range("path_join_tolerance", value, 0.0, 10.0)?;
+    }
+
+    if let Some(value) = matches.value_of("stroke_width") {
+        config.stroke_mode = StrokeMode::Stroked;
+        config.stroke_width = Some(parse_arg_in_range("stroke_width", value, 0.0, 100.0)?);
+    }
+
+    if let Some(value) = matches.value_of("background_color") {
+        config.background_color = Some(parse_arg("background_color", value)?);
+    }
+
+    if let Some(value) = matches.value_of("color_palette") {
+        config.palette_file = Some(PathBuf::from(value));
+    }
+
+    if let Some(value) = matches.value_of("layer_limit") {
+        config.layer_limit =
+            Some(parse_arg_in_range::<u32>("layer_limit", value, 1, u32::MAX)? as usize);
+    }
+
+    if let Some(value) = matches.value_of("max_paths_per_cluster") {
+        config.max_paths_per_cluster =
+            Some(parse_arg_in_range::<u32>("max_paths_per_cluster", value, 1, u32::MAX)? as usize);
+    }
+
+    if matches.is_present("anti_alias") {
+        config.anti_alias = true;
+    }
+
+    if matches.is_present("invert") {
+        config.invert = true;
+    }
+
+    if matches.is_present("ignore_alpha") {
+        config.ignore_alpha = true;
+    }
+
+    if let Some(value) = matches.value_of("alpha_threshold") {
+        config.alpha_threshold = parse_arg("alpha_threshold", value)?;
+    }
+
+    if let Some(value) = matches.value_of("threshold") {
+        if value.trim() == "otsu" {
+            config.binary_threshold_otsu = true;
         } else {
-            panic!(
-                "Parser Error: Path precision is not an unsigned integer: {}.",
-                value
-            );
+            config.binary_threshold = parse_arg("threshold", value)?;
+        }
+    }
+
+    if matches.is_present("dither") {
+        config.dither = true;
+    }
+
+    if matches.is_present("canny") {
+        config.canny_preprocess = true;
+    }
+
+    if let Some(value) = matches.value_of("canny_high_threshold") {
+        config.canny_high_threshold =
+            parse_arg_in_range("canny_high_threshold", value, 0.0, 255.0)?;
+    }
+
+    if let Some(value) = matches.value_of("canny_low_threshold") {
+        config.canny_low_threshold = parse_arg_in_range(
+            "canny_low_threshold",
+            value,
+            0.0,
+            config.canny_high_threshold,
+        )?;
+    }
+
+    if let Some(value) = matches.value_of("grayscale_gamma") {
+        config.grayscale_gamma = parse_arg_in_range("grayscale_gamma", value, 0.1, 3.0)?;
+    }
+
+    if let Some(value) = matches.value_of("grayscale_weights") {
+        let invalid = || VTracerError::ParseError {
+            param: "grayscale_weights",
+            raw: value.to_owned(),
+        };
+        let weights = value
+            .split(',')
+            .map(|weight| weight.trim().parse::<f64>().map_err(|_| invalid()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let [r, g, b] = weights[..] else {
+            return Err(invalid());
+        };
+        let sum = r + g + b;
+        if !(0.99..=1.01).contains(&sum) {
+            return Err(VTracerError::OutOfRange {
+                param: "grayscale_weights",
+                value: sum,
+                min: 0.99,
+                max: 1.01,
+            });
         }
+        config.grayscale_weights = (r, g, b);
     }
 
-    (input_path, output_path, config)
+    if let Some(value) = matches.value_of("resize_to") {
+        let invalid = || VTracerError::ParseError {
+            param: "resize_to",
+            raw: value.to_owned(),
+        };
+        let (width, height) = value.split_once(['x', 'X']).ok_or_else(invalid)?;
+        let width: u32 = width.trim().parse().map_err(|_| invalid())?;
+        let height: u32 = height.trim().parse().map_err(|_| invalid())?;
+        if width == 0 || height == 0 {
+            return Err(invalid());
+        }
+        config.resize_to = Some((width, height));
+    }
+
+    if let Some(value) = matches.value_of("region") {
+        config.region = Some(parse_arg("region", value)?);
+    }
+
+    if let Some(value) = matches.value_of("aspect_ratio") {
+        config.aspect_ratio = Some(parse_arg("aspect_ratio", value)?);
+    }
+
+    if let Some(value) = matches.value_of("aspect_ratio_fit") {
+        config.aspect_ratio_fit = parse_arg("aspect_ratio_fit", value)?;
+    }
+
+    if let Some(value) = matches.value_of("opacity") {
+        config.global_opacity = parse_arg_in_range("opacity", value, 0.0, 1.0)?;
+    }
+
+    if let Some(value) = matches.value_of("blend_mode") {
+        config.blend_mode = Some(parse_arg("blend_mode", value)?);
+    }
+
+    if let Some(value) = matches.value_of("viewbox_margin") {
+        config.viewbox_margin = parse_arg("viewbox_margin", value)?;
+    }
+
+    if let Some(value) = matches.value_of("translate") {
+        let invalid = || VTracerError::ParseError {
+            param: "translate",
+            raw: value.to_owned(),
+        };
+        let (x, y) = value.split_once(',').ok_or_else(invalid)?;
+        let x = x.trim().parse().map_err(|_| invalid())?;
+        let y = y.trim().parse().map_err(|_| invalid())?;
+        config.translate = Some((x, y));
+    }
+
+    if matches.is_present("clip_to_bounds") {
+        config.clip_to_bounds = true;
+    }
+
+    if let Some(value) = matches.value_of("scale") {
+        let scale: f64 = parse_arg("scale", value)?;
+        if !scale.is_finite() || scale <= 0.0 {
+            return Err(VTracerError::ParseError {
+                param: "scale",
+                raw: value.to_owned(),
+            });
+        }
+        config.scale = scale;
+    }
+
+    if let Some(value) = matches.value_of("unit") {
+        config.unit = Some(parse_arg("unit", value)?);
+    }
+
+    if let Some(value) = matches.value_of("encoding") {
+        config.output_encoding = parse_arg("encoding", value)?;
+    }
+
+    if let Some(value) = matches.value_of("output_indent") {
+        config.output_indent = Some(parse_arg_in_range("output_indent", value, 0, 16)?);
+    }
+
+    if let Some(value) = matches.value_of("max_file_size") {
+        let max_bytes = parse_arg_in_range::<u32>("max_file_size", value, 1, u32::MAX)?;
+        config.max_output_bytes = Some(max_bytes as usize);
+    }
+
+    if matches.is_present("layer_names") {
+        config.layer_names = true;
+    }
+
+    if let Some(value) = matches.value_of("id_prefix") {
+        if !vtracer::is_valid_id_prefix(value) {
+            return Err(VTracerError::ParseError {
+                param: "id_prefix",
+                raw: value.to_owned(),
+            });
+        }
+        config.output_id_prefix = value.to_owned();
+    }
+
+    if matches.is_present("embed_original") {
+        config.embed_original = true;
+    }
+
+    if let Some(value) = matches.value_of("svg_title") {
+        config.svg_title = Some(value.to_owned());
+    }
+
+    if let Some(value) = matches.value_of("svg_desc") {
+        config.svg_desc = Some(value.to_owned());
+    }
+
+    if matches.is_present("optimize_svg") {
+        config.optimize_svg = true;
+    }
+
+    if matches.is_present("dedupe_paths") {
+        config.dedupe_paths = true;
+    }
+
+    if matches.is_present("no_preserve_dpi") {
+        config.preserve_dpi = false;
+    }
+
+    if let Some(value) = matches.value_of("sort_paths_by") {
+        config.path_sort = parse_arg("sort_paths_by", value)?;
+    }
+
+    if matches.is_present("no_close_path") {
+        config.close_paths = false;
+    }
+
+    if matches.is_present("parallel") {
+        config.parallel = true;
+    }
+
+    if let Some(value) = matches.value_of("cpu_threads") {
+        config.cpu_threads =
+            Some(parse_arg_in_range::<u32>("cpu_threads", value, 1, u32::MAX)? as usize);
+    }
+
+    if let Some(value) = matches.value_of("seed") {
+        config.random_seed = Some(parse_arg("seed", value)?);
+    }
+
+    if let Some(value) = matches.value_of("save_config") {
+        config.save_toml(value)?;
+    }
+
+    let batch = matches.is_present("batch") || input_path.is_dir();
+    let timeout = match matches.value_of("timeout") {
+        Some(value) => Some(Duration::from_secs(parse_arg("timeout", value)?)),
+        None => None,
+    };
+    let recursive = matches.is_present("recursive");
+    let progress = matches.is_present("progress");
+    let profile = matches.is_present("profile");
+    let watch = matches.is_present("watch");
+    if watch && (batch || !input_path.is_file()) {
+        return Err(VTracerError::ParseError {
+            param: "watch",
+            raw: String::from("given without an input file"),
+        });
+    }
+    let log_level = if matches.is_present("quiet") {
+        LevelFilter::Error
+    } else if matches.is_present("verbose") {
+        LevelFilter::Debug
+    } else {
+        LevelFilter::Info
+    };
+    let gif_start = match matches.value_of("gif_start") {
+        Some(value) => parse_arg("gif_start", value)?,
+        None => 0,
+    };
+    let gif_frames = match matches.value_of("gif_frames") {
+        Some(value) => Some(parse_arg("gif_frames", value)?),
+        None => None,
+    };
+
+    Ok(Args {
+        input_path,
+        output_path,
+        config,
+        batch,
+        recursive,
+        progress,
+        profile,
+        gif_start,
+        gif_frames,
+        log_level,
+        timeout,
+        watch,
+    })
+}
+
+fn is_stdio(path: &Path) -> bool {
+    path.as_os_str() == STDIO_PATH
+}
+
+fn is_url(path: &Path) -> bool {
+    let path = path.to_string_lossy();
+    path.starts_with("http://") || path.starts_with("https://")
+}
+
+/// Download the body of `url`, which must answer 200 OK
+#[cfg(feature = "fetch")]
+fn fetch(url: &str, timeout: Option<Duration>) -> Result<Vec<u8>, String> {
+    let mut agent = ureq::AgentBuilder::new();
+    if let Some(timeout) = timeout {
+        agent = agent.timeout(timeout);
+    }
+    let response = match agent.build().get(url).call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(status, _)) => {
+            return Err(format!("{} answered with status {}", url, status))
+        }
+        // The error starts with the URL
+        Err(err) => return Err(format!("Cannot download {}", err)),
+    };
+    if response.status() != 200 {
+        return Err(format!(
+            "{} answered with status {}",
+            url,
+            response.status()
+        ));
+    }
+    let mut bytes = vec![];
+    response
+        .into_reader()
+        .read_to_end(&mut bytes)
+        .map_err(|err| format!("Cannot download {}: {}", url, err))?;
+    Ok(bytes)
+}
+
+#[cfg(not(feature = "fetch"))]
+fn fetch(url: &str, _timeout: Option<Duration>) -> Result<Vec<u8>, String> {
+    Err(format!(
+        "Cannot download {}: vtracer was built without the fetch feature",
+        url
+    ))
+}
+
+fn convert_with_streams(
+    input_path: &Path,
+    output_path: &Path,
+    config: Config,
+    reporter: Option<Box<dyn ProgressReporter>>,
+    timeout: Option<Duration>,
+) -> Result<(), String> {
+    let reader: Box<dyn Read> = if is_stdio(input_path) {
+        Box::new(io::stdin().lock())
+    } else if is_url(input_path) {
+        Box::new(Cursor::new(fetch(&input_path.to_string_lossy(), timeout)?))
+    } else {
+        match File::open(input_path) {
+            Ok(file) => Box::new(file),
+            Err(_) => return Err(String::from("No image file found at specified input path")),
+        }
+    };
+    let writer: Box<dyn Write> = if is_stdio(output_path) {
+        Box::new(io::stdout().lock())
+    } else {
+        match File::create(output_path) {
+            Ok(file) => Box::new(file),
+            Err(_) => return Err(String::from("Cannot create output file.")),
+        }
+    };
+    vtracer::convert_image_stream_to_svg(reader, writer, config, reporter)
+}
+
+fn batch_main(args: Args) {
+    let result = vtracer::batch_convert(
+        &args.input_path,
+        &args.output_path,
+        args.config,
+        args.recursive,
+    );
+    log::info!("Converted {} image(s).", result.converted.len());
+    if !result.is_ok() {
+        for (path, msg) in &result.failed {
+            log::error!("Conversion failed for {}: {}", path.display(), msg);
+        }
+        log::error!("{} image(s) failed to convert.", result.failed.len());
+        std::process::exit(1);
+    }
+}
+
+/// Convert the input of `args`, whether an image or the frames of an animated GIF, and
+/// return the message to report on success. Animated GIFs are converted frame by frame
+/// into a numbered series of files.
+fn convert_input(args: &Args) -> Result<String, String> {
+    if is_gif(&args.input_path)
+        && !is_url(&args.input_path)
+        && !is_stdio(&args.output_path)
+        && (args.gif_start > 0 || args.gif_frames.is_some() || is_animated_gif(&args.input_path))
+    {
+        let written = vtracer::convert_gif_frames(
+            &args.input_path,
+            &args.output_path,
+            args.config.clone(),
+            args.gif_start,
+            args.gif_frames,
+        )?;
+        return Ok(format!("Converted {} frame(s).", written.len()));
+    }
+    let reporter: Option<Box<dyn ProgressReporter>> = match (args.progress, args.profile) {
+        (_, true) => Some(Box::new(StageTimer::new(args.progress))),
+        (true, false) => Some(Box::new(StderrProgress::default())),
+        (false, false) => None,
+    };
+    let (input_path, output_path) = (&args.input_path, &args.output_path);
+    let streamed =
+        reporter.is_some() || is_stdio(input_path) || is_stdio(output_path) || is_url(input_path);
+    // Only the file conversion writes the layers of each color
+    if streamed && !args.config.split_by_color {
+        convert_with_streams(
+            input_path,
+            output_path,
+            args.config.clone(),
+            reporter,
+            args.timeout,
+        )?;
+    } else {
+        vtracer::convert_image_to_svg_with_progress(
+            input_path,
+            output_path,
+            args.config.clone(),
+            reporter,
+        )?;
+    }
+    Ok(String::from("Conversion successful."))
+}
+
+/// Convert the input, then again whenever it is written to, until interrupted.
+/// Failed conversions are reported and the watch goes on.
+#[cfg(feature = "watch")]
+fn watch_main(args: Args) {
+    use notify::{EventKind, RecursiveMode, Watcher};
+    use std::sync::mpsc;
+
+    let (sender, receiver) = mpsc::channel();
+    // Editors often save by replacing the file, which ends a watch on the file itself
+    let directory = match args.input_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let watching = notify::recommended_watcher(sender).and_then(|mut watcher| {
+        watcher.watch(directory, RecursiveMode::NonRecursive)?;
+        Ok(watcher)
+    });
+    let _watcher = match watching {
+        Ok(watcher) => watcher,
+        Err(err) => {
+            log::error!("Cannot watch {}: {}", args.input_path.display(), err);
+            std::process::exit(1);
+        }
+    };
+    let file_name = args.input_path.file_name();
+    let is_input_written = |event: &notify::Result<notify::Event>| match event {
+        Ok(event) => {
+            matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+                && event.paths.iter().any(|path| path.file_name() == file_name)
+        }
+        Err(_) => false,
+    };
+
+    watch_convert(&args);
+    log::info!("Watching {} for changes", args.input_path.display());
+    while let Ok(event) = receiver.recv() {
+        if !is_input_written(&event) {
+            continue;
+        }
+        // A save raises several events; wait for them to settle
+        while receiver.recv_timeout(Duration::from_millis(100)).is_ok() {}
+        watch_convert(&args);
+    }
+}
+
+#[cfg(not(feature = "watch"))]
+fn watch_main(_args: Args) {
+    log::error!("Cannot watch the input: vtracer was built without the watch feature");
+    std::process::exit(1);
+}
+
+/// One conversion of `watch_main`, reported with the time of day and its duration
+#[cfg(feature = "watch")]
+fn watch_convert(args: &Args) {
+    let start = Instant::now();
+    match convert_input(args) {
+        Ok(msg) => log::info!(
+            "[{}] {} Took {}ms.",
+            utc_time_of_day(),
+            msg,
+            start.elapsed().as_millis()
+        ),
+        Err(msg) => log::error!("[{}] {}", utc_time_of_day(), VTracerError::Conversion(msg)),
+    }
+}
+
+/// The current time as `HH:MM:SS`, in UTC
+#[cfg(feature = "watch")]
+fn utc_time_of_day() -> String {
+    let seconds = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        % 86400;
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+fn is_gif(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("gif"))
+}
+
+fn is_svgz(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("svgz"))
+}
+
+/// Messages go to stderr as plain lines, keeping stdout free for `--output -`.
+/// `RUST_LOG` overrides `level`.
+fn init_logger(level: LevelFilter) {
+    env_logger::Builder::new()
+        .filter_level(level)
+        .parse_default_env()
+        .format(|buf, record| writeln!(buf, "{}", record.args()))
+        .init();
 }
 
 fn main() {
-    let (input_path, output_path, config) = config_from_args();
-    let result = converter::convert_image_to_svg(&input_path, &output_path, config);
-    match result {
-        Ok(()) => {
-            println!("Conversion successful.");
+    let args = match config_from_args() {
+        Ok(args) => args,
+        Err(err) => {
+            init_logger(LevelFilter::Error);
+            log::error!("{}", err);
+            std::process::exit(1);
+        }
+    };
+    init_logger(args.log_level);
+    log::debug!("Tracing with {}", args.config);
+    for (field, value) in args.config.diff_from_default() {
+        log::debug!("Changed from default: {} = {}", field, value);
+    }
+    log::debug!(
+        "Converter parameters:\n{}",
+        ConverterConfig::from(args.config.clone())
+    );
+    for warning in args.config.validate() {
+        match warning.severity {
+            Severity::Error => log::error!("{}: {}", warning.severity, warning),
+            Severity::Warning => log::warn!("{}: {}", warning.severity, warning),
+            Severity::Hint => log::info!("{}: {}", warning.severity, warning),
         }
+    }
+    if let Some(threads) = args.config.cpu_threads {
+        if let Err(err) = ThreadPoolBuilder::new().num_threads(threads).build_global() {
+            log::warn!("Cannot size the thread pool: {}", err);
+        }
+    }
+    if args.batch {
+        return batch_main(args);
+    }
+    if args.watch {
+        return watch_main(args);
+    }
+    match convert_input(&args) {
+        Ok(msg) => log::info!("{}", msg),
         Err(msg) => {
-            panic!("Conversion failed with error message: {}", msg);
+            log::error!("{}", VTracerError::Conversion(msg));
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_finite_args_are_out_of_range() {
+        for raw in ["NaN", "inf", "-inf"] {
+            let result = parse_arg_in_range("global_opacity", raw, 0.0, f64::INFINITY);
+            assert!(
+                matches!(result, Err(VTracerError::OutOfRange { .. })),
+                "{}",
+                raw
+            );
         }
+        assert_eq!(
+            parse_arg_in_range("global_opacity", "0.5", 0.0, 1.0).unwrap(),
+            0.5
+        );
     }
 }
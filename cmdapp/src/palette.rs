@@ -0,0 +1,312 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::path::Path;
+
+use super::config::Rgba;
+use visioncortex::Color;
+
+/// CIE L*a*b* color under the D65 white point
+#[derive(Clone, Copy)]
+pub(crate) struct Lab {
+    pub(crate) l: f64,
+    pub(crate) a: f64,
+    pub(crate) b: f64,
+}
+
+struct Entry {
+    /// Area weighted sum of the sRGB channels
+    sum: [f64; 3],
+    weight: f64,
+    lab: Lab,
+    merged_into: Option<usize>,
+}
+
+impl Entry {
+    fn mean(&self) -> [f64; 3] {
+        self.sum.map(|channel| channel / self.weight)
+    }
+}
+
+/// Reduce the colors of `layers` (color and area of each layer) to at most `max_colors`
+/// distinct colors, by repeatedly merging the two closest colors by CIEDE2000 distance.
+/// Returns the new color of every layer, in the same order.
+pub(crate) fn reduce_colors(layers: &[(Color, usize)], max_colors: usize) -> Vec<Color> {
+    let mut index_of = HashMap::new();
+    let mut entries: Vec<Entry> = vec![];
+    let layer_entries: Vec<usize> = layers
+        .iter()
+        .map(|&(color, area)| {
+            let index = *index_of
+                .entry((color.r, color.g, color.b))
+                .or_insert_with(|| {
+                    entries.push(Entry {
+                        sum: [0.0; 3],
+                        weight: 0.0,
+                        lab: rgb_to_lab([color.r as f64, color.g as f64, color.b as f64]),
+                        merged_into: None,
+                    });
+                    entries.len() - 1
+                });
+            let weight = area.max(1) as f64;
+            let entry = &mut entries[index];
+            entry.sum[0] += color.r as f64 * weight;
+            entry.sum[1] += color.g as f64 * weight;
+            entry.sum[2] += color.b as f64 * weight;
+            entry.weight += weight;
+            index
+        })
+        .collect();
+
+    let max_colors = max_colors.max(1);
+    let mut num_colors = entries.len();
+    if num_colors > max_colors {
+        // Nearest alive neighbour of every alive entry
+        let mut nearest: Vec<(usize, f64)> = (0..entries.len())
+            .map(|i| find_nearest(&entries, i))
+            .collect();
+
+        while num_colors > max_colors {
+            let (keep, _) = nearest
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| entries[*i].merged_into.is_none())
+                .min_by(|(_, x), (_, y)| x.1.total_cmp(&y.1))
+                .unwrap();
+            let remove = nearest[keep].0;
+
+            let (sum, weight) = (entries[remove].sum, entries[remove].weight);
+            let entry = &mut entries[keep];
+            for (channel, other) in entry.sum.iter_mut().zip(sum) {
+                *channel += other;
+            }
+            entry.weight += weight;
+            entry.lab = rgb_to_lab(entry.mean());
+            entries[remove].merged_into = Some(keep);
+            num_colors -= 1;
+
+            nearest[keep] = find_nearest(&entries, keep);
+            for i in 0..entries.len() {
+                if i == keep || entries[i].merged_into.is_some() {
+                    continue;
+                }
+                if nearest[i].0 == keep || nearest[i].0 == remove {
+                    nearest[i] = find_nearest(&entries, i);
+                } else {
+                    let distance = ciede2000(entries[i].lab, entries[keep].lab);
+                    if distance < nearest[i].1 {
+                        nearest[i] = (keep, distance);
+                    }
+                }
+            }
+        }
+    }
+
+    layers
+        .iter()
+        .zip(layer_entries)
+        .map(|(&(color, _), mut index)| {
+            while let Some(parent) = entries[index].merged_into {
+                index = parent;
+            }
+            let [r, g, b] = entries[index].mean().map(|channel| channel.round() as u8);
+            Color::new_rgba(r, g, b, color.a)
+        })
+        .collect()
+}
+
+/// Load the colors of a GIMP `.gpl` palette, or of a text file of one `#RRGGBB` color per line
+pub(crate) fn load_palette(path: &Path) -> Result<Vec<Color>, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|_| String::from("No palette file found at specified path"))?;
+    let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+    let is_gpl = text.lines().next().map(str::trim) == Some("GIMP Palette");
+    if is_gpl {
+        lines.next();
+    }
+
+    let mut palette = vec![];
+    for line in lines {
+        let color = if is_gpl {
+            // Header fields and comments, then `R G B name` entries
+            if line.starts_with('#') || line.contains(':') {
+                continue;
+            }
+            let channels: Vec<u8> = line
+                .split_whitespace()
+                .take(3)
+                .map_while(|channel| channel.parse().ok())
+                .collect();
+            match channels[..] {
+                [r, g, b] => Color::new(r, g, b),
+                _ => return Err(format!("Invalid palette entry: {}", line)),
+            }
+        } else {
+            let color: Rgba = line.parse()?;
+            Color::new(color.r, color.g, color.b)
+        };
+        palette.push(color);
+    }
+    if palette.is_empty() {
+        return Err(String::from("The palette has no colors."));
+    }
+    Ok(palette)
+}
+
+/// The color of `palette` closest to `color` by CIEDE2000 distance, with the alpha of `color`
+pub(crate) fn nearest_color(color: Color, palette: &[Color]) -> Color {
+    let lab = |c: Color| rgb_to_lab([c.r as f64, c.g as f64, c.b as f64]);
+    let target = lab(color);
+    let nearest = palette
+        .iter()
+        .min_by(|x, y| ciede2000(target, lab(**x)).total_cmp(&ciede2000(target, lab(**y))))
+        .copied()
+        .unwrap_or(color);
+    Color::new_rgba(nearest.r, nearest.g, nearest.b, color.a)
+}
+
+/// Hue of an sRGB color in degrees, in [0, 360). Grays have a hue of -1 to sort first.
+pub(crate) fn hue(color: Color) -> f64 {
+    let [r, g, b] = [color.r, color.g, color.b].map(|c| c as f64);
+    let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+    if max == min {
+        return -1.0;
+    }
+    let hue = if max == r {
+        (g - b) / (max - min)
+    } else if max == g {
+        2.0 + (b - r) / (max - min)
+    } else {
+        4.0 + (r - g) / (max - min)
+    };
+    (hue * 60.0).rem_euclid(360.0)
+}
+
+fn find_nearest(entries: &[Entry], i: usize) -> (usize, f64) {
+    entries
+        .iter()
+        .enumerate()
+        .filter(|(j, entry)| *j != i && entry.merged_into.is_none())
+        .map(|(j, entry)| (j, ciede2000(entries[i].lab, entry.lab)))
+        .min_by(|x, y| x.1.total_cmp(&y.1))
+        .unwrap_or((i, f64::INFINITY))
+}
+
+pub(crate) fn rgb_to_lab(rgb: [f64; 3]) -> Lab {
+    let [r, g, b] = rgb.map(|channel| {
+        let c = channel / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    });
+    let x = (0.4124564 * r + 0.3575761 * g + 0.1804375 * b) / 0.95047;
+    let y = 0.2126729 * r + 0.7151522 * g + 0.0721750 * b;
+    let z = (0.0193339 * r + 0.119192 * g + 0.9503041 * b) / 1.08883;
+
+    let f = |t: f64| {
+        if t > 216.0 / 24389.0 {
+            t.cbrt()
+        } else {
+            (24389.0 / 27.0 * t + 16.0) / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    Lab {
+        l: 116.0 * fy - 16.0,
+        a: 500.0 * (fx - fy),
+        b: 200.0 * (fy - fz),
+    }
+}
+
+/// CIEDE2000 color difference, with unit weighting factors
+fn ciede2000(lab1: Lab, lab2: Lab) -> f64 {
+    let c1 = lab1.a.hypot(lab1.b);
+    let c2 = lab2.a.hypot(lab2.b);
+    let c_mean7 = ((c1 + c2) / 2.0).powi(7);
+    let g = 0.5 * (1.0 - (c_mean7 / (c_mean7 + 25f64.powi(7))).sqrt());
+
+    let a1 = (1.0 + g) * lab1.a;
+    let a2 = (1.0 + g) * lab2.a;
+    let c1 = a1.hypot(lab1.b);
+    let c2 = a2.hypot(lab2.b);
+    let hue = |b: f64, a: f64| {
+        if a == 0.0 && b == 0.0 {
+            0.0
+        } else {
+            b.atan2(a).to_degrees().rem_euclid(360.0)
+        }
+    };
+    let h1 = hue(lab1.b, a1);
+    let h2 = hue(lab2.b, a2);
+
+    let delta_l = lab2.l - lab1.l;
+    let delta_c = c2 - c1;
+    let delta_h = if c1 * c2 == 0.0 {
+        0.0
+    } else if (h2 - h1).abs() <= 180.0 {
+        h2 - h1
+    } else if h2 <= h1 {
+        h2 - h1 + 360.0
+    } else {
+        h2 - h1 - 360.0
+    };
+    let delta_h = 2.0 * (c1 * c2).sqrt() * (delta_h / 2.0).to_radians().sin();
+
+    let l_mean = (lab1.l + lab2.l) / 2.0;
+    let c_mean = (c1 + c2) / 2.0;
+    let h_mean = if c1 * c2 == 0.0 {
+        h1 + h2
+    } else if (h1 - h2).abs() <= 180.0 {
+        (h1 + h2) / 2.0
+    } else if h1 + h2 < 360.0 {
+        (h1 + h2 + 360.0) / 2.0
+    } else {
+        (h1 + h2 - 360.0) / 2.0
+    };
+
+    let t = 1.0 - 0.17 * (h_mean - 30.0).to_radians().cos()
+        + 0.24 * (2.0 * h_mean).to_radians().cos()
+        + 0.32 * (3.0 * h_mean + 6.0).to_radians().cos()
+        - 0.20 * (4.0 * h_mean - 63.0).to_radians().cos();
+    let delta_theta = 30.0 * (-((h_mean - 275.0) / 25.0).powi(2)).exp();
+    let c_mean7 = c_mean.powi(7);
+    let r_c = 2.0 * (c_mean7 / (c_mean7 + 25f64.powi(7))).sqrt();
+    let l_offset = (l_mean - 50.0).powi(2);
+    let s_l = 1.0 + 0.015 * l_offset / (20.0 + l_offset).sqrt();
+    let s_c = 1.0 + 0.045 * c_mean;
+    let s_h = 1.0 + 0.015 * c_mean * t;
+    let r_t = -(2.0 * delta_theta * PI / 180.0).sin() * r_c;
+
+    let l = delta_l / s_l;
+    let c = delta_c / s_c;
+    let h = delta_h / s_h;
+    (l * l + c * c + h * h + r_t * c * h).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn reduce_colors_caps_the_color_count() {
+        let layers: Vec<(Color, usize)> = (0..40u8)
+            .map(|i| (Color::new(i * 6, 255 - i * 6, i * 3), 10 + i as usize))
+            .collect();
+        for max_colors in [2, 5, 16] {
+            let colors = reduce_colors(&layers, max_colors);
+            assert_eq!(colors.len(), layers.len());
+            let distinct: HashSet<(u8, u8, u8)> = colors.iter().map(|c| (c.r, c.g, c.b)).collect();
+            assert!(distinct.len() <= max_colors, "{} colors", distinct.len());
+        }
+    }
+
+    #[test]
+    fn reduce_colors_keeps_colors_under_the_cap() {
+        let layers = [(Color::new(255, 0, 0), 10), (Color::new(0, 0, 255), 20)];
+        let colors = reduce_colors(&layers, 4);
+        assert_eq!(colors[0], layers[0].0);
+        assert_eq!(colors[1], layers[1].0);
+    }
+}
@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use visioncortex::{CompoundPath, PathF64, PointF64, Spline};
+
+use super::config::Rgba;
+
+/// A `<path>` element read back from an SVG
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedPath {
+    /// The fill color, or the stroke color of paths that are not filled, inherited from the
+    /// enclosing groups if the path has none. Opaque black if it is not a hex color.
+    pub color: Rgba,
+    /// The `d` attribute
+    pub d: String,
+    /// The `transform` attribute
+    pub transform: Option<String>,
+}
+
+impl ParsedPath {
+    /// The path in the representation the converter traces into, moved by `transform`.
+    /// Polygons become `PathF64` and curves become `Spline`.
+    /// Only absolute path commands and `translate` transforms are understood.
+    pub fn to_compound_path(&self) -> Result<CompoundPath, String> {
+        let offset = match &self.transform {
+            Some(transform) => match parse_translate(transform) {
+                Some((x, y)) => PointF64::new(x, y),
+                None => return Err(format!("Unsupported transform {}", transform)),
+            },
+            None => PointF64::default(),
+        };
+        let mut compound = CompoundPath::new();
+        let mut subpath: Option<Subpath> = None;
+        let mut command = None;
+        let mut tokens = tokenize(&self.d)?.into_iter().peekable();
+        while let Some(token) = tokens.peek() {
+            command = match (token, command) {
+                (Token::Command(c), _) => {
+                    let c = *c;
+                    tokens.next();
+                    Some(c)
+                }
+                // Coordinates following those of a command repeat it, as lines after a move
+                (Token::Number(_), Some('M')) => Some('L'),
+                (Token::Number(_), Some(c)) if c != 'Z' => Some(c),
+                (Token::Number(_), _) => return Err(format!("Unexpected number in {}", self.d)),
+            };
+            let mut next_point = || match (tokens.next(), tokens.next()) {
+                (Some(Token::Number(x)), Some(Token::Number(y))) => {
+                    Ok(PointF64::new(x, y) + offset)
+                }
+                _ => Err(format!("Missing coordinates in path data {}", self.d)),
+            };
+            match command.unwrap_or_default() {
+                'M' => {
+                    if let Some(subpath) = subpath.take() {
+                        subpath.add_to(&mut compound);
+                    }
+                    subpath = Some(Subpath::new(next_point()?));
+                }
+                'L' | 'C' | 'Z' if subpath.is_none() => {
+                    return Err(format!("Path data {} does not start with M", self.d));
+                }
+                'L' => subpath.as_mut().unwrap().line_to(next_point()?),
+                'C' => {
+                    let (a, b, c) = (next_point()?, next_point()?, next_point()?);
+                    subpath.as_mut().unwrap().curve_to(a, b, c);
+                }
+                'Z' => subpath.take().unwrap().add_to(&mut compound),
+                other => return Err(format!("Unsupported path command {}", other)),
+            }
+        }
+        if let Some(subpath) = subpath {
+            subpath.add_to(&mut compound);
+        }
+        Ok(compound)
+    }
+}
+
+/// Extract every `<path>` of an SVG, in document order.
+/// Malformed markup is skipped rather than reported, and `<use>` references are not followed.
+pub fn svg_to_paths(svg: &str) -> Vec<ParsedPath> {
+    path_tags(svg)
+        .into_iter()
+        .map(|(tag, color)| ParsedPath {
+            color: color
+                .and_then(|color| Rgba::from_str(&color).ok())
+                .unwrap_or(Rgba {
+                    r: 0,
+                    g: 0,
+                    b: 0,
+                    a: 255,
+                }),
+            d: attribute(tag, "d").unwrap_or_default().to_owned(),
+            transform: attribute(tag, "transform").map(String::from),
+        })
+        .collect()
+}
+
+/// The `<path>` tags of `svg` with their paint, inherited from the enclosing groups
+pub(crate) fn path_tags(svg: &str) -> Vec<(&str, Option<String>)> {
+    let mut tags = vec![];
+    // Colors of the enclosing groups, which paths without their own color inherit
+    let mut group_colors: Vec<Option<String>> = vec![];
+    let mut rest = svg;
+    while let Some(start) = rest.find('<') {
+        let Some(end) = rest[start..].find('>') else {
+            break;
+        };
+        let tag = &rest[start + 1..start + end];
+        rest = &rest[start + end + 1..];
+
+        if tag.starts_with("/g") {
+            group_colors.pop();
+        } else if tag.starts_with("g ") || tag == "g" {
+            let color = paint(tag).or_else(|| group_colors.last().cloned().flatten());
+            if !tag.ends_with('/') {
+                group_colors.push(color);
+            }
+        } else if tag.starts_with("path ") {
+            let color = paint(tag).or_else(|| group_colors.last().cloned().flatten());
+            tags.push((tag, color));
+        }
+    }
+    tags
+}
+
+/// The fill color, or the stroke color of paths that are not filled
+fn paint(tag: &str) -> Option<String> {
+    match attribute(tag, "fill") {
+        Some("none") | None => attribute(tag, "stroke").map(String::from),
+        Some(fill) => Some(String::from(fill)),
+    }
+}
+
+pub(crate) fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let pattern = format!(" {}=\"", name);
+    let start = tag.find(&pattern)? + pattern.len();
+    let length = tag[start..].find('"')?;
+    Some(&tag[start..start + length])
+}
+
+pub(crate) fn parse_translate(transform: &str) -> Option<(f64, f64)> {
+    let arguments = transform
+        .trim()
+        .strip_prefix("translate(")?
+        .strip_suffix(')')?;
+    let mut numbers = arguments
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse::<f64>());
+    let x = numbers.next()?.ok()?;
+    let y = numbers.next().unwrap_or(Ok(0.0)).ok()?;
+    Some((x, y))
+}
+
+/// Number of times each command of the path data `d` is applied, counting the repetitions
+/// implied by extra coordinates. Coordinates repeating a move are lines, as in SVG.
+pub(crate) fn command_counts(d: &str) -> Result<HashMap<char, usize>, String> {
+    let mut counts = HashMap::new();
+    let mut command = None;
+    // Numbers read since the current command was last counted
+    let mut numbers = 0;
+    for token in tokenize(d)? {
+        match token {
+            Token::Command(c) => {
+                *counts.entry(c).or_insert(0) += 1;
+                command = Some(c);
+                numbers = 0;
+            }
+            Token::Number(_) => {
+                let Some(c) = command else {
+                    return Err(format!("Unexpected number in {}", d));
+                };
+                let arguments = match c.to_ascii_uppercase() {
+                    'H' | 'V' => 1,
+                    'M' | 'L' | 'T' => 2,
+                    'S' | 'Q' => 4,
+                    'C' => 6,
+                    'A' => 7,
+                    _ => return Err(format!("Unexpected number in {}", d)),
+                };
+                numbers += 1;
+                if numbers > arguments {
+                    let repeated = match c {
+                        'M' => 'L',
+                        'm' => 'l',
+                        c => c,
+                    };
+                    *counts.entry(repeated).or_insert(0) += 1;
+                    command = Some(repeated);
+                    numbers = 1;
+                }
+            }
+        }
+    }
+    Ok(counts)
+}
+
+enum Token {
+    Command(char),
+    Number(f64),
+}
+
+fn tokenize(data: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut number = String::new();
+    for c in data.chars() {
+        if c.is_ascii_alphabetic() && c != 'e' && c != 'E' {
+            push_number(&mut number, &mut tokens)?;
+            tokens.push(Token::Command(c));
+        } else if c == ',' || c.is_whitespace() {
+            push_number(&mut number, &mut tokens)?;
+        } else {
+            // A sign starts a new number unless it belongs to an exponent
+            if c == '-' && !number.is_empty() && !number.ends_with(['e', 'E']) {
+                push_number(&mut number, &mut tokens)?;
+            }
+            number.push(c);
+        }
+    }
+    push_number(&mut number, &mut tokens)?;
+    Ok(tokens)
+}
+
+/// Parse the digits collected in `number`, if any, and start a new number
+fn push_number(number: &mut String, tokens: &mut Vec<Token>) -> Result<(), String> {
+    if !number.is_empty() {
+        match number.parse() {
+            Ok(value) => tokens.push(Token::Number(value)),
+            Err(_) => return Err(format!("Invalid number {} in path data", number)),
+        }
+        number.clear();
+    }
+    Ok(())
+}
+
+/// A subpath being read, as a polygon until its first curve
+struct Subpath {
+    points: Vec<PointF64>,
+    is_spline: bool,
+}
+
+impl Subpath {
+    fn new(start: PointF64) -> Self {
+        Self {
+            points: vec![start],
+            is_spline: false,
+        }
+    }
+
+    fn line_to(&mut self, point: PointF64) {
+        if self.is_spline {
+            let last = self.points[self.points.len() - 1];
+            let third = (point - last) * (1.0 / 3.0);
+            self.points
+                .extend([last + third, last + third * 2.0, point]);
+        } else {
+            self.points.push(point);
+        }
+    }
+
+    fn curve_to(&mut self, a: PointF64, b: PointF64, c: PointF64) {
+        if !self.is_spline {
+            // Turn the lines so far into straight curves
+            let polygon = std::mem::take(&mut self.points);
+            self.points.push(polygon[0]);
+            self.is_spline = true;
+            for &point in &polygon[1..] {
+                self.line_to(point);
+            }
+        }
+        self.points.extend([a, b, c]);
+    }
+
+    fn add_to(self, compound: &mut CompoundPath) {
+        if self.is_spline {
+            let mut spline = Spline::new(self.points[0]);
+            for curve in self.points[1..].chunks_exact(3) {
+                spline.add(curve[0], curve[1], curve[2]);
+            }
+            compound.add_spline(spline);
+        } else {
+            // Closed polygons repeat the first point at the end
+            let mut points = self.points;
+            points.push(points[0]);
+            compound.add_path_f64(PathF64::from_points(points));
+        }
+    }
+}
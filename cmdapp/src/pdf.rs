@@ -0,0 +1,152 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use pdf_writer::{Content, Date, Pdf, Rect, Ref, TextStr};
+
+use super::svg::SvgFile;
+use super::vector::{PathSegment, VectorPath};
+
+const CATALOG_ID: Ref = Ref::new(1);
+const PAGE_TREE_ID: Ref = Ref::new(2);
+const PAGE_ID: Ref = Ref::new(3);
+const CONTENT_ID: Ref = Ref::new(4);
+const INFO_ID: Ref = Ref::new(5);
+
+/// Single page PDF rendering of the paths of an `SvgFile`.
+/// The media box is the image size in pixels, one pixel per PDF unit.
+pub struct PdfFile<'a> {
+    svg: &'a SvgFile,
+    title: Option<String>,
+}
+
+impl<'a> PdfFile<'a> {
+    pub fn new(svg: &'a SvgFile) -> Self {
+        Self { svg, title: None }
+    }
+
+    /// Set the document title written into the metadata
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let (width, height) = (self.svg.width as f32, self.svg.height as f32);
+
+        let mut content = Content::new();
+        // SVG has its origin at the top left corner, PDF at the bottom left
+        content.transform([1.0, 0.0, 0.0, -1.0, 0.0, height]);
+        // Transparency would need an extended graphics state, so alpha is only honoured as on or off
+        if let Some(color) = self.svg.background_color.filter(|color| color.a > 0) {
+            content.set_fill_rgb(
+                color.r as f32 / 255.0,
+                color.g as f32 / 255.0,
+                color.b as f32 / 255.0,
+            );
+            content.rect(0.0, 0.0, width, height);
+            content.fill_nonzero();
+        }
+        if let Some(width) = self.svg.stroke_width {
+            content.set_line_width(width.max(0.0) as f32);
+        }
+        for path in VectorPath::from_svg(self.svg) {
+            if let Some(color) = path.fill.or(path.stroke) {
+                let (r, g, b) = (
+                    color.r as f32 / 255.0,
+                    color.g as f32 / 255.0,
+                    color.b as f32 / 255.0,
+                );
+                if path.stroke.is_some() {
+                    content.set_stroke_rgb(r, g, b);
+                } else {
+                    content.set_fill_rgb(r, g, b);
+                }
+            }
+            for segment in path.segments {
+                match segment {
+                    PathSegment::MoveTo(p) => {
+                        content.move_to(p.x as f32, p.y as f32);
+                    }
+                    PathSegment::LineTo(p) => {
+                        content.line_to(p.x as f32, p.y as f32);
+                    }
+                    PathSegment::CubicBezierTo {
+                        control1,
+                        control2,
+                        end,
+                    } => {
+                        content.cubic_to(
+                            control1.x as f32,
+                            control1.y as f32,
+                            control2.x as f32,
+                            control2.y as f32,
+                            end.x as f32,
+                            end.y as f32,
+                        );
+                    }
+                    PathSegment::ClosePath => {
+                        content.close_path();
+                    }
+                }
+            }
+            if path.stroke.is_some() {
+                content.stroke();
+            } else {
+                content.fill_nonzero();
+            }
+        }
+        let content = content.finish();
+
+        let mut pdf = Pdf::new();
+        pdf.catalog(CATALOG_ID).pages(PAGE_TREE_ID);
+        pdf.pages(PAGE_TREE_ID).kids([PAGE_ID]).count(1);
+        pdf.page(PAGE_ID)
+            .parent(PAGE_TREE_ID)
+            .media_box(Rect::new(0.0, 0.0, width, height))
+            .contents(CONTENT_ID)
+            .resources();
+        pdf.stream(CONTENT_ID, &content);
+
+        let mut info = pdf.document_info(INFO_ID);
+        if let Some(title) = &self.title {
+            info.title(TextStr(title));
+        }
+        info.creator(TextStr(concat!(
+            "visioncortex VTracer ",
+            env!("CARGO_PKG_VERSION")
+        )));
+        if let Some(date) = current_date() {
+            info.creation_date(date);
+        }
+        drop(info);
+
+        pdf.finish()
+    }
+}
+
+/// The current UTC date and time, or `None` if the system clock is before 1970
+fn current_date() -> Option<Date> {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    let (days, secs_of_day) = (secs / 86400, secs % 86400);
+
+    // Civil date from days since the epoch, in 400 year eras starting on March 1st
+    let days = days + 719468;
+    let era = days / 146097;
+    let day_of_era = days % 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    Some(
+        Date::new(year as u16)
+            .month(month as u8)
+            .day(day as u8)
+            .hour((secs_of_day / 3600) as u8)
+            .minute((secs_of_day / 60 % 60) as u8)
+            .second((secs_of_day % 60) as u8)
+            .utc_offset_hour(0),
+    )
+}
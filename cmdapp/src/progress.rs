@@ -0,0 +1,51 @@
+/// Stages of a conversion, reported in this order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Reading and decoding the input image. Only reported when converting files and streams.
+    Decoding,
+    /// Grouping pixels of the same quantized color (thresholding in binary mode)
+    Quantization,
+    /// Merging pixel groups hierarchically into color layers
+    Clustering,
+    /// Tracing every layer into paths
+    PathBuilding,
+    /// Joining, filtering, ordering and merging the traced paths. visioncortex fits each
+    /// path into polygons or splines as it is traced, so the fitting itself is reported as
+    /// part of `PathBuilding`.
+    PathSimplification,
+    /// Writing the output. Only reported when converting files and streams.
+    Serialization,
+}
+
+/// Lowercase words, such as `path building`
+impl std::fmt::Display for Stage {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Decoding => "decoding",
+            Self::Quantization => "quantization",
+            Self::Clustering => "clustering",
+            Self::PathBuilding => "path building",
+            Self::PathSimplification => "path simplification",
+            Self::Serialization => "serialization",
+        })
+    }
+}
+
+/// Receives progress updates from a long-running conversion
+pub trait ProgressReporter {
+    /// `fraction` is within [0,1] and reaches 1 exactly once, when `stage` is complete
+    fn on_progress(&self, stage: Stage, fraction: f32);
+}
+
+impl<F: Fn(Stage, f32)> ProgressReporter for F {
+    fn on_progress(&self, stage: Stage, fraction: f32) {
+        self(stage, fraction)
+    }
+}
+
+/// Reporter used when the caller does not ask for progress
+pub(crate) struct NoProgress;
+
+impl ProgressReporter for NoProgress {
+    fn on_progress(&self, _stage: Stage, _fraction: f32) {}
+}
@@ -1,14 +1,13 @@
 use crate::*;
 use pyo3::prelude::*;
 use std::path::PathBuf;
-use visioncortex::PathSimplifyMode;
 
 /// Python binding
 #[pyfunction]
 fn convert_image_to_svg_py(
     image_path: &str,
     out_path: &str,
-    colormode: Option<&str>,       // "color" or "binary"
+    colormode: Option<&str>,       // "color", "binary" or "grayscale"
     hierarchical: Option<&str>,    // "stacked" or "cutout"
     mode: Option<&str>,            // "polygon", "spline", "none"
     filter_speckle: Option<usize>, // default: 4
@@ -28,6 +27,7 @@ fn convert_image_to_svg_py(
     let color_mode = match colormode.unwrap_or("color") {
         "color" => ColorMode::Color,
         "binary" => ColorMode::Binary,
+        "grayscale" => ColorMode::Grayscale,
         _ => ColorMode::Color,
     };
 
@@ -41,6 +41,7 @@ fn convert_image_to_svg_py(
         "spline" => PathSimplifyMode::Spline,
         "polygon" => PathSimplifyMode::Polygon,
         "none" => PathSimplifyMode::None,
+        "bezier" => PathSimplifyMode::Bezier,
         _ => PathSimplifyMode::Spline,
     };
 
@@ -0,0 +1,22 @@
+use resvg::usvg::{Options, Tree};
+use tiny_skia::{Pixmap, Transform};
+
+use super::error::VTracerError;
+use super::trace::encode_png;
+
+/// Rasterize `svg` into an RGBA PNG of `width` by `height` pixels, for thumbnails, previews
+/// and comparisons with the input. The document is stretched to that size, so pass the
+/// dimensions of the image it was traced from to keep its proportions.
+pub fn render_to_png(svg: &str, width: u32, height: u32) -> Result<Vec<u8>, VTracerError> {
+    let tree = Tree::from_str(svg, &Options::default())
+        .map_err(|err| VTracerError::Conversion(format!("Cannot parse the SVG: {}", err)))?;
+    let mut pixmap = Pixmap::new(width, height)
+        .ok_or_else(|| VTracerError::Conversion(String::from("Cannot render an empty image.")))?;
+    let size = tree.size();
+    let transform =
+        Transform::from_scale(width as f32 / size.width(), height as f32 / size.height());
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+
+    encode_png(&pixmap)
+        .map_err(|_| VTracerError::Conversion(String::from("Cannot encode the render as PNG.")))
+}
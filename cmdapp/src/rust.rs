@@ -0,0 +1,95 @@
+use std::fmt;
+
+use super::config::Rgba;
+use super::svg::SvgFile;
+use super::vector::{round_number, PathSegment, VectorPath};
+
+/// Rust source rendering of the paths of an `SvgFile`, as `pub const PATHS: &[Path]` with the
+/// types of the `vtracer-runtime` crate, next to the image size in `WIDTH` and `HEIGHT`.
+/// Coordinates are in pixels of the traced image, without the margin, scale and translation
+/// of the SVG.
+pub struct RustFile<'a> {
+    svg: &'a SvgFile,
+}
+
+impl<'a> RustFile<'a> {
+    pub fn new(svg: &'a SvgFile) -> Self {
+        Self { svg }
+    }
+}
+
+impl fmt::Display for RustFile<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "// Generated by visioncortex VTracer {}",
+            env!("CARGO_PKG_VERSION")
+        )?;
+        writeln!(f, "use vtracer_runtime::{{Color, Path, Segment}};")?;
+        writeln!(f)?;
+        writeln!(f, "/// Width of the traced image, in pixels")?;
+        writeln!(f, "pub const WIDTH: u32 = {};", self.svg.width)?;
+        writeln!(f, "/// Height of the traced image, in pixels")?;
+        writeln!(f, "pub const HEIGHT: u32 = {};", self.svg.height)?;
+        writeln!(f, "/// Width of the outlines of stroked paths")?;
+        match self.svg.stroke_width {
+            Some(width) => writeln!(
+                f,
+                "pub const STROKE_WIDTH: Option<f32> = Some({});",
+                number(width)
+            )?,
+            None => writeln!(f, "pub const STROKE_WIDTH: Option<f32> = None;")?,
+        }
+        writeln!(f)?;
+        writeln!(f, "pub const PATHS: &[Path] = &[")?;
+        for path in VectorPath::from_svg(self.svg) {
+            writeln!(f, "    Path {{")?;
+            writeln!(f, "        fill: {},", color(path.fill))?;
+            writeln!(f, "        stroke: {},", color(path.stroke))?;
+            writeln!(f, "        segments: &[")?;
+            for segment in &path.segments {
+                writeln!(f, "            {},", segment_string(segment))?;
+            }
+            writeln!(f, "        ],")?;
+            writeln!(f, "    }},")?;
+        }
+        writeln!(f, "];")
+    }
+}
+
+fn segment_string(segment: &PathSegment) -> String {
+    match segment {
+        PathSegment::MoveTo(point) => {
+            format!("Segment::MoveTo({}, {})", number(point.x), number(point.y))
+        }
+        PathSegment::LineTo(point) => {
+            format!("Segment::LineTo({}, {})", number(point.x), number(point.y))
+        }
+        PathSegment::CubicBezierTo {
+            control1,
+            control2,
+            end,
+        } => format!(
+            "Segment::CubicTo({}, {}, {}, {}, {}, {})",
+            number(control1.x),
+            number(control1.y),
+            number(control2.x),
+            number(control2.y),
+            number(end.x),
+            number(end.y)
+        ),
+        PathSegment::ClosePath => String::from("Segment::Close"),
+    }
+}
+
+fn color(color: Option<Rgba>) -> String {
+    match color {
+        Some(Rgba { r, g, b, a }) => format!("Some(Color::rgba({}, {}, {}, {}))", r, g, b, a),
+        None => String::from("None"),
+    }
+}
+
+/// `value` rounded to 3 decimal places as an `f32` literal, which needs a decimal point
+fn number(value: f64) -> String {
+    format!("{:?}", round_number(value) as f32)
+}
@@ -0,0 +1,184 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use visioncortex::{CompoundPath, CompoundPathElement, PointF64};
+
+use super::config::{Rect, Rgba};
+use super::converter::{compound_path_length, cubic_bezier_point};
+use super::parse::{command_counts, svg_to_paths};
+use super::svg::SvgFile;
+
+/// Number of line segments each curve is flattened into to measure it
+const SPLINE_STEPS: usize = 8;
+
+/// Distribution of the color layers of a converted image, to help tune `color_precision`
+/// and `layer_difference`. Areas are in square pixels of the input image. In stacked mode
+/// a layer's area includes the parts covered by the layers above it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ColorStats {
+    /// Number of paths
+    pub layer_count: usize,
+    pub largest_layer_area: usize,
+    pub smallest_layer_area: usize,
+    pub mean_layer_area: f64,
+    /// Total area of each fill color, largest first
+    pub color_histogram: Vec<(Rgba, usize)>,
+}
+
+impl ColorStats {
+    /// Measure the paths of `svg`
+    pub fn from_svg(svg: &SvgFile) -> Self {
+        if svg.paths.is_empty() {
+            return Self::default();
+        }
+        let areas: Vec<usize> = svg
+            .paths
+            .iter()
+            .map(|path| area(&path.path).round() as usize)
+            .collect();
+
+        let mut histogram: Vec<(Rgba, usize)> = vec![];
+        let mut positions = HashMap::new();
+        for (path, &area) in svg.paths.iter().zip(&areas) {
+            let color = Rgba {
+                r: path.color.r,
+                g: path.color.g,
+                b: path.color.b,
+                a: path.color.a,
+            };
+            let key = [color.r, color.g, color.b, color.a];
+            let position = *positions.entry(key).or_insert_with(|| {
+                histogram.push((color, 0));
+                histogram.len() - 1
+            });
+            histogram[position].1 += area;
+        }
+        // Stable, so colors of equal area stay in stacking order
+        histogram.sort_by_key(|&(_, area)| Reverse(area));
+
+        Self {
+            layer_count: areas.len(),
+            largest_layer_area: areas.iter().copied().max().unwrap_or_default(),
+            smallest_layer_area: areas.iter().copied().min().unwrap_or_default(),
+            mean_layer_area: areas.iter().sum::<usize>() as f64 / areas.len() as f64,
+            color_histogram: histogram,
+        }
+    }
+}
+
+/// Size of the paths of an SVG, to compare simplification settings and budget file sizes
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PathStatistics {
+    /// Number of `<path>` elements
+    pub total_paths: usize,
+    /// Number of points the path commands other than `Z` end on, control points excluded
+    pub total_nodes: usize,
+    /// Number of times each path command letter is applied, implicit repetitions included
+    pub total_commands: HashMap<char, usize>,
+    /// Mean length of the outlines of a path, subpaths added up
+    pub average_path_length_px: f64,
+    /// Smallest rectangle of whole pixels holding every path, clamped to the positive quadrant
+    pub bounding_box: Rect,
+}
+
+/// Count and measure the `<path>` elements of `svg`, in user units.
+/// Commands are counted in every path, but only the paths `ParsedPath::to_compound_path`
+/// can read, without arcs or relative commands, are measured. `<use>` references are
+/// not followed, so with `dedupe_paths` each shape is counted once.
+pub fn path_statistics(svg: &str) -> PathStatistics {
+    let mut statistics = PathStatistics::default();
+    let mut total_length = 0.0;
+    let mut measured = 0;
+    let (mut min, mut max) = (
+        PointF64::new(f64::INFINITY, f64::INFINITY),
+        PointF64::new(f64::NEG_INFINITY, f64::NEG_INFINITY),
+    );
+    for path in svg_to_paths(svg) {
+        statistics.total_paths += 1;
+        if let Ok(counts) = command_counts(&path.d) {
+            for (command, count) in counts {
+                *statistics.total_commands.entry(command).or_insert(0) += count;
+                if !command.eq_ignore_ascii_case(&'z') {
+                    statistics.total_nodes += count;
+                }
+            }
+        }
+        let Ok(compound) = path.to_compound_path() else {
+            continue;
+        };
+        total_length += compound_path_length(&compound);
+        measured += 1;
+        for point in compound.paths.iter().flat_map(outline_points) {
+            min = PointF64::new(min.x.min(point.x), min.y.min(point.y));
+            max = PointF64::new(max.x.max(point.x), max.y.max(point.y));
+        }
+    }
+    if measured > 0 {
+        statistics.average_path_length_px = total_length / measured as f64;
+    }
+    if min.x <= max.x && min.y <= max.y {
+        let (left, top) = (min.x.floor().max(0.0), min.y.floor().max(0.0));
+        let (right, bottom) = (max.x.ceil().max(left), max.y.ceil().max(top));
+        statistics.bounding_box = Rect {
+            x: left as u32,
+            y: top as u32,
+            width: (right - left) as u32,
+            height: (bottom - top) as u32,
+        };
+    }
+    statistics
+}
+
+/// Points along the outline of `element`, with curves flattened
+fn outline_points(element: &CompoundPathElement) -> Vec<PointF64> {
+    match element {
+        CompoundPathElement::PathI32(p) => p.path.iter().map(|p| p.to_point_f64()).collect(),
+        CompoundPathElement::PathF64(p) => p.path.clone(),
+        CompoundPathElement::Spline(s) => {
+            let mut points: Vec<PointF64> = s.points.first().copied().into_iter().collect();
+            for curve in s.points.windows(4).step_by(3) {
+                points.extend(
+                    (1..=SPLINE_STEPS)
+                        .map(|i| cubic_bezier_point(curve, i as f64 / SPLINE_STEPS as f64)),
+                );
+            }
+            points
+        }
+    }
+}
+
+/// Area enclosed by `path`. Holes are traced in the opposite direction to the outlines
+/// around them, so the signed areas of the subpaths add up to the filled area.
+pub(crate) fn area(path: &CompoundPath) -> f64 {
+    path.paths
+        .iter()
+        .map(|element| match element {
+            CompoundPathElement::PathI32(p) => {
+                let points: Vec<_> = p.path.iter().map(|p| p.to_point_f64()).collect();
+                signed_area(&points)
+            }
+            CompoundPathElement::PathF64(p) => signed_area(&p.path),
+            CompoundPathElement::Spline(s) => {
+                let mut points = vec![];
+                for curve in s.points.windows(4).step_by(3) {
+                    points.extend(
+                        (0..SPLINE_STEPS)
+                            .map(|i| cubic_bezier_point(curve, i as f64 / SPLINE_STEPS as f64)),
+                    );
+                }
+                signed_area(&points)
+            }
+        })
+        .sum::<f64>()
+        .abs()
+}
+
+/// Shoelace formula, with the polygon closed from the last point back to the first
+fn signed_area(points: &[PointF64]) -> f64 {
+    let mut sum = 0.0;
+    for (i, a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        sum += a.x * b.y - b.x * a.y;
+    }
+    sum / 2.0
+}
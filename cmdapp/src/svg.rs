@@ -1,13 +1,80 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use std::collections::HashMap;
 use std::fmt;
-use visioncortex::{Color, CompoundPath, PointF64};
 
+use super::config::{BlendMode, OutputEncoding, Unit};
+use visioncortex::{
+    Color, CompoundPath, CompoundPathElement, NumberFormat, PathF64, PathI32, PointF64, Spline,
+    ToSvgString,
+};
+
+#[derive(Clone)]
 pub struct SvgFile {
     pub paths: Vec<SvgPath>,
     pub width: usize,
     pub height: usize,
     pub path_precision: Option<u32>,
+    /// When set, paths are written as outlines of this width instead of filled regions
+    pub stroke_width: Option<f64>,
+    /// Painted as a full-bleed rectangle behind every path
+    pub background_color: Option<Color>,
+    /// Opacity of everything drawn, background included
+    pub opacity: f64,
+    /// Blend mode of every layer, or of every path without `layer_names`
+    pub blend_mode: Option<BlendMode>,
+    /// Padding added around the image, on all four sides
+    pub viewbox_margin: u32,
+    /// Offset of the paths, in user units
+    pub translate: Option<(f64, f64)>,
+    /// Clip the paths to the image rectangle, inside the margin
+    pub clip_to_bounds: bool,
+    /// Factor applied to the dimensions and every coordinate, margin included
+    pub scale: f64,
+    /// Unit of `width` and `height`; when set, the viewBox holds the size in pixels
+    pub unit: Option<Unit>,
+    /// Resolution `width` and `height` are converted to `unit` at
+    pub dpi: f64,
+    /// Wrap every path in a named Inkscape layer
+    pub layer_names: bool,
+    /// Put before every `id`, followed by a dash unless empty
+    pub id_prefix: String,
+    /// Source raster written as a hidden `<image>` below the paths, centered on the canvas
+    pub original: Option<EmbeddedImage>,
+    /// Accessible name of the image, written as `<title>`
+    pub title: Option<String>,
+    /// Accessible description of the image, written as `<desc>`
+    pub description: Option<String>,
+    /// Write the style attributes shared by the paths once, on a parent `<g>`
+    pub group_attributes: bool,
+    /// Write the outlines shared by several paths once, as a `<symbol>` placed with `<use>`.
+    /// Closed subpaths are compared from their topmost, then leftmost point, but subpaths
+    /// listed in a different order make different outlines.
+    pub dedupe_paths: bool,
+    /// Declared in the XML declaration, and used by `to_bytes`
+    pub encoding: OutputEncoding,
+    /// End open subpaths, whose first and last points are not adjacent, with `Z` too.
+    /// Enclosed subpaths are always closed.
+    pub close_paths: bool,
+    /// Radius of the arcs that replace the corners of polygons, in user units. 0 for none.
+    pub corner_rounding: f64,
+    /// Spaces per nesting level when every element is written on its own line.
+    /// None for the compact layout.
+    pub indent: Option<u32>,
+    /// Times the image was traced again with more speckles filtered to fit in
+    /// `ConverterConfig::max_output_bytes`. Not written to the file.
+    pub simplify_iterations: usize,
+}
+
+/// A PNG encoded raster image
+#[derive(Clone)]
+pub struct EmbeddedImage {
+    pub png: Vec<u8>,
+    pub width: usize,
+    pub height: usize,
 }
 
+#[derive(Clone)]
 pub struct SvgPath {
     pub path: CompoundPath,
     pub color: Color,
@@ -20,30 +87,523 @@ impl SvgFile {
             width,
             height,
             path_precision,
+            stroke_width: None,
+            background_color: None,
+            opacity: 1.0,
+            blend_mode: None,
+            viewbox_margin: 0,
+            translate: None,
+            clip_to_bounds: false,
+            scale: 1.0,
+            unit: None,
+            dpi: 96.0,
+            layer_names: false,
+            id_prefix: String::from("vt"),
+            original: None,
+            title: None,
+            description: None,
+            group_attributes: false,
+            dedupe_paths: false,
+            encoding: OutputEncoding::Utf8,
+            close_paths: true,
+            corner_rounding: 0.0,
+            indent: None,
+            simplify_iterations: 0,
         }
     }
 
     pub fn add_path(&mut self, path: CompoundPath, color: Color) {
         self.paths.push(SvgPath { path, color })
     }
+
+    /// One file per color, holding the paths of that color and the rest of this file,
+    /// in the order the colors first appear in the stacking order.
+    /// Colors differing only in alpha share a file, as paths are written without alpha.
+    pub fn split_by_color(mut self) -> Vec<(Color, SvgFile)> {
+        let paths = std::mem::take(&mut self.paths);
+        let mut layers: Vec<(Color, SvgFile)> = vec![];
+        for path in paths {
+            let same_color = |color: &Color| {
+                (color.r, color.g, color.b) == (path.color.r, path.color.g, path.color.b)
+            };
+            match layers.iter_mut().find(|(color, _)| same_color(color)) {
+                Some((_, layer)) => layer.paths.push(path),
+                None => {
+                    let mut layer = self.clone();
+                    let color = path.color;
+                    layer.paths.push(path);
+                    layers.push((color, layer));
+                }
+            }
+        }
+        layers
+    }
+
+    /// Drop zero-length subpaths, merge consecutive paths of the same color into one,
+    /// and write the attributes shared by the paths once
+    pub fn optimize(&mut self) {
+        let mut paths: Vec<SvgPath> = vec![];
+        for mut path in self.paths.drain(..) {
+            path.path.paths.retain(|element| !is_zero_length(element));
+            if path.path.is_empty() {
+                continue;
+            }
+            match paths.last_mut() {
+                Some(last) if last.color == path.color => last.path.append(path.path),
+                _ => paths.push(path),
+            }
+        }
+        self.paths = paths;
+        self.group_attributes = true;
+    }
+
+    /// Chain the open subpaths of each color whose ends lie within `tolerance` pixels of
+    /// one another into single subpaths, closing the chains that come back to their start.
+    /// Subpaths are joined in their own direction, and only polygons with polygons and
+    /// curves with curves. Paths left without subpaths are dropped.
+    pub fn join_open_paths(&mut self, tolerance: f64) {
+        let is_open = |element: &CompoundPathElement| {
+            let (first, last) = end_points(element);
+            first != last
+        };
+        // Open subpaths, taken out of their paths with the index of the path they were in
+        let mut open: Vec<(usize, Vec<PointF64>, bool)> = vec![];
+        for (index, path) in self.paths.iter_mut().enumerate() {
+            let (opened, closed) = std::mem::take(&mut path.path.paths)
+                .into_iter()
+                .partition(is_open);
+            path.path.paths = closed;
+            open.extend(opened.into_iter().map(|element| match element {
+                CompoundPathElement::PathI32(p) => (
+                    index,
+                    p.path.iter().map(|p| p.to_point_f64()).collect(),
+                    false,
+                ),
+                CompoundPathElement::PathF64(p) => (index, p.path, false),
+                CompoundPathElement::Spline(s) => (index, s.points, true),
+            }));
+        }
+        let within = |a: PointF64, b: PointF64| a.distance_to(b) <= tolerance;
+
+        let mut chains: Vec<(usize, Vec<PointF64>, bool)> = vec![];
+        let mut used = vec![false; open.len()];
+        for i in 0..open.len() {
+            if used[i] {
+                continue;
+            }
+            used[i] = true;
+            let (index, mut points, is_spline) = open[i].clone();
+            let color = self.paths[index].color;
+            let joinable = |j: usize, used: &[bool]| {
+                !used[j] && open[j].2 == is_spline && self.paths[open[j].0].color == color
+            };
+            loop {
+                let (first, last) = (points[0], points[points.len() - 1]);
+                if let Some(j) =
+                    (0..open.len()).find(|&j| joinable(j, &used) && within(last, open[j].1[0]))
+                {
+                    // The gap closes onto the end of the chain
+                    used[j] = true;
+                    points.extend_from_slice(&open[j].1[1..]);
+                } else if let Some(j) = (0..open.len())
+                    .find(|&j| joinable(j, &used) && within(open[j].1[open[j].1.len() - 1], first))
+                {
+                    used[j] = true;
+                    let mut joined = open[j].1.clone();
+                    joined.extend_from_slice(&points[1..]);
+                    points = joined;
+                } else {
+                    break;
+                }
+            }
+            let last = points.len() - 1;
+            if last > 0 && within(points[last], points[0]) {
+                // Closed subpaths repeat the first point at the end
+                points[last] = points[0];
+            }
+            chains.push((index, points, is_spline));
+        }
+
+        for (index, points, is_spline) in chains {
+            let path = &mut self.paths[index].path;
+            if is_spline {
+                let mut spline = Spline::new(points[0]);
+                for curve in points[1..].chunks_exact(3) {
+                    spline.add(curve[0], curve[1], curve[2]);
+                }
+                path.add_spline(spline);
+            } else {
+                path.add_path_f64(PathF64::from_points(points));
+            }
+        }
+        self.paths.retain(|path| !path.path.is_empty());
+    }
+
+    /// The SVG encoded as `encoding`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let svg = self.to_string();
+        match self.encoding {
+            OutputEncoding::Utf8 => svg.into_bytes(),
+            // Characters beyond ISO-8859-1 were escaped while formatting
+            OutputEncoding::Latin1 => svg.chars().map(|c| c as u8).collect(),
+        }
+    }
+
+    /// Escape the markup characters of `text`, and the characters `encoding` cannot represent
+    fn escape_xml(&self, text: &str) -> String {
+        let text = escape_xml(text);
+        match self.encoding {
+            OutputEncoding::Utf8 => text,
+            OutputEncoding::Latin1 => text
+                .chars()
+                .map(|c| match c {
+                    '\0'..='\u{ff}' => c.to_string(),
+                    _ => format!("&#x{:X};", c as u32),
+                })
+                .collect(),
+        }
+    }
+
+    /// `name` with the id prefix
+    fn id(&self, name: &str) -> String {
+        if self.id_prefix.is_empty() {
+            String::from(name)
+        } else {
+            format!("{}-{}", self.id_prefix, name)
+        }
+    }
+
+    fn style(&self, path: &SvgPath) -> String {
+        match self.stroke_width {
+            Some(width) => format!(
+                r#"fill="none" stroke="currentColor" stroke-width="{}""#,
+                width * self.scale
+            ),
+            None => format!(r#"fill="{}""#, path.color.to_hex_string()),
+        }
+    }
+}
+
+fn is_zero_length(element: &CompoundPathElement) -> bool {
+    match element {
+        CompoundPathElement::PathI32(p) => p.path.iter().all(|point| *point == p.path[0]),
+        CompoundPathElement::PathF64(p) => p.path.iter().all(|point| *point == p.path[0]),
+        CompoundPathElement::Spline(s) => s.points.iter().all(|point| *point == s.points[0]),
+    }
+}
+
+pub(crate) fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
 }
 
 impl fmt::Display for SvgFile {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        writeln!(f, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        match self.indent {
+            Some(indent) => f.write_str(&indent_xml(&Compact(self).to_string(), indent as usize)),
+            None => self.fmt_compact(f),
+        }
+    }
+}
+
+/// The layout `SvgFile` is written in before indenting
+struct Compact<'a>(&'a SvgFile);
+
+impl fmt::Display for Compact<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt_compact(f)
+    }
+}
+
+/// `xml` with every tag on its own line, indented by `indent` spaces per nesting level.
+/// Elements holding only text, such as `<title>`, stay on one line.
+fn indent_xml(xml: &str, indent: usize) -> String {
+    let mut output = String::with_capacity(xml.len());
+    let mut depth: usize = 0;
+    // Whether the last line is an open element, so far empty or holding only text
+    let mut open_line = false;
+    let mut rest = xml;
+    while let Some(start) = rest.find('<') {
+        let text = &rest[..start];
+        let Some(end) = rest[start..].find('>') else {
+            break;
+        };
+        let tag = &rest[start..start + end + 1];
+        rest = &rest[start + end + 1..];
+
+        if !text.trim().is_empty() {
+            output.push_str(text);
+        }
+        if tag.starts_with("</") {
+            depth = depth.saturating_sub(1);
+            if !open_line {
+                output.push('\n');
+                output.push_str(&" ".repeat(depth * indent));
+            }
+            output.push_str(tag);
+            open_line = false;
+        } else {
+            if !output.is_empty() {
+                output.push('\n');
+            }
+            output.push_str(&" ".repeat(depth * indent));
+            output.push_str(tag);
+            open_line = !tag.ends_with("/>") && !tag.starts_with("<?") && !tag.starts_with("<!");
+            if open_line {
+                depth += 1;
+            }
+        }
+    }
+    output.push('\n');
+    output
+}
+
+impl SvgFile {
+    fn fmt_compact(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let encoding = match self.encoding {
+            OutputEncoding::Utf8 => "UTF-8",
+            OutputEncoding::Latin1 => "ISO-8859-1",
+        };
+        writeln!(f, r#"<?xml version="1.0" encoding="{}"?>"#, encoding)?;
         writeln!(
             f,
             r#"<!-- Generator: visioncortex VTracer {} -->"#,
             env!("CARGO_PKG_VERSION")
         )?;
-        writeln!(
-            f,
-            r#"<svg version="1.1" xmlns="http://www.w3.org/2000/svg" width="{}" height="{}">"#,
-            self.width, self.height
-        )?;
+        let margin = self.viewbox_margin as usize;
+        let (width, height) = (
+            (self.width + 2 * margin) as f64 * self.scale,
+            (self.height + 2 * margin) as f64 * self.scale,
+        );
+        match self.unit {
+            Some(unit) => {
+                let length = |pixels: f64| {
+                    let length = pixels / self.dpi * unit.per_inch();
+                    format!("{}{}", (length * 1e4).round() / 1e4, unit.suffix())
+                };
+                write!(
+                    f,
+                    r#"<svg version="1.1" xmlns="http://www.w3.org/2000/svg" width="{}" height="{}""#,
+                    length(width),
+                    length(height)
+                )?;
+            }
+            None => write!(
+                f,
+                r#"<svg version="1.1" xmlns="http://www.w3.org/2000/svg" width="{}" height="{}""#,
+                width, height
+            )?,
+        }
+        if margin > 0 || self.scale != 1.0 || self.unit.is_some() {
+            write!(f, r#" viewBox="0 0 {} {}""#, width, height)?;
+        }
+        if self.layer_names {
+            write!(
+                f,
+                r#" xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape""#
+            )?;
+        }
+        if self.dedupe_paths {
+            write!(f, r#" xmlns:xlink="http://www.w3.org/1999/xlink""#)?;
+        }
+        writeln!(f, ">")?;
 
-        for path in &self.paths {
-            path.fmt_with_precision(f, self.path_precision)?;
+        // Assistive technology reads these only as the first children of the root
+        if let Some(title) = &self.title {
+            writeln!(f, "<title>{}</title>", self.escape_xml(title))?;
+        }
+        if let Some(description) = &self.description {
+            writeln!(f, "<desc>{}</desc>", self.escape_xml(description))?;
+        }
+
+        // Grouped as a whole, so overlapping paths do not show through each other
+        if self.opacity < 1.0 {
+            writeln!(f, r#"<g opacity="{}">"#, self.opacity)?;
+        }
+
+        if let Some(image) = &self.original {
+            // Same centering as the padding or cropping to an aspect ratio
+            let x = (self.width as isize - image.width as isize) / 2 + margin as isize;
+            let y = (self.height as isize - image.height as isize) / 2 + margin as isize;
+            writeln!(
+                f,
+                r#"<image x="{}" y="{}" width="{}" height="{}" href="data:image/png;base64,{}" visibility="hidden"/>"#,
+                x as f64 * self.scale,
+                y as f64 * self.scale,
+                image.width as f64 * self.scale,
+                image.height as f64 * self.scale,
+                BASE64.encode(&image.png)
+            )?;
+        }
+
+        if let Some(color) = &self.background_color {
+            write!(
+                f,
+                r#"<rect width="{}" height="{}" fill="{}""#,
+                width,
+                height,
+                color.to_hex_string()
+            )?;
+            if color.a != 255 {
+                write!(f, r#" fill-opacity="{}""#, color.a as f64 / 255.0)?;
+            }
+            writeln!(f, "/>")?;
+        }
+
+        // Inkscape only treats top level groups as layers, so layers are clipped one by one
+        let clip_id = self.id("img-bounds");
+        let clip = format!(r#" clip-path="url(#{})""#, clip_id);
+        let (layer_clip, root_clip) = match (self.clip_to_bounds, self.layer_names) {
+            (false, _) => ("", false),
+            (true, true) => (clip.as_str(), false),
+            (true, false) => ("", true),
+        };
+        if self.clip_to_bounds {
+            writeln!(
+                f,
+                r#"<defs><clipPath id="{}"><rect x="{}" y="{}" width="{}" height="{}"/></clipPath></defs>"#,
+                clip_id,
+                margin as f64 * self.scale,
+                margin as f64 * self.scale,
+                self.width as f64 * self.scale,
+                self.height as f64 * self.scale
+            )?;
+        }
+        // Layers are moved one by one, for the same reason as they are clipped
+        let (layer_transform, root_transform) = match (self.translate, self.layer_names) {
+            (None, _) => (String::new(), None),
+            (Some((x, y)), true) => (format!(r#" transform="translate({} {})""#, x, y), None),
+            (Some((x, y)), false) => (String::new(), Some((x, y))),
+        };
+        // Outside the clip, which moves with the paths as it does on layers
+        if let Some((x, y)) = root_transform {
+            writeln!(f, r#"<g transform="translate({} {})">"#, x, y)?;
+        }
+        if root_clip {
+            writeln!(f, "<g{}>", clip)?;
+        }
+
+        let shape = PathShape {
+            close_paths: self.close_paths,
+            corner_rounding: self.corner_rounding,
+        };
+        // Path data is relative to the first point, so equal strings are equal shapes
+        let data: Vec<(String, PointF64)> = if self.dedupe_paths {
+            self.paths
+                .iter()
+                .map(|path| {
+                    let path = SvgPath {
+                        path: normalize_start(&path.path),
+                        color: path.color,
+                    };
+                    path.to_svg_data(self.path_precision, &shape, margin as f64, self.scale)
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for (string, _) in &data {
+            *counts.entry(string).or_default() += 1;
+        }
+        let mut symbols: HashMap<&str, usize> = HashMap::new();
+        let mut shapes: Vec<&str> = vec![];
+        for (string, _) in &data {
+            if counts[string.as_str()] > 1 && !symbols.contains_key(string.as_str()) {
+                shapes.push(string);
+                symbols.insert(string, shapes.len());
+            }
+        }
+        if !shapes.is_empty() {
+            writeln!(f, "<defs>")?;
+            // Outlines extend on all sides of their first point, which is the symbol origin
+            for (i, string) in shapes.iter().enumerate() {
+                writeln!(
+                    f,
+                    r#"<symbol id="{}" overflow="visible"><path d="{}"/></symbol>"#,
+                    self.id(&format!("shape-{}", i + 1)),
+                    string
+                )?;
+            }
+            writeln!(f, "</defs>")?;
+        }
+
+        let styles: Vec<String> = self.paths.iter().map(|path| self.style(path)).collect();
+        // Layers carry their own style, otherwise a single group does if every path agrees
+        let shared_style = self.group_attributes
+            && !self.layer_names
+            && styles.len() > 1
+            && styles.iter().all(|style| *style == styles[0]);
+        if shared_style {
+            writeln!(f, "<g {}>", styles[0])?;
+        }
+        // On the paths themselves without layers, as a shared group would blend as a whole
+        let blend = self
+            .blend_mode
+            .map(|mode| format!(r#"style="mix-blend-mode:{}""#, mode));
+        for (i, (path, style)) in self.paths.iter().zip(&styles).enumerate() {
+            if self.layer_names {
+                write!(
+                    f,
+                    r#"<g id="{}" inkscape:groupmode="layer" inkscape:label="Color {}"{}{}"#,
+                    self.id(&format!("layer-{}", i + 1)),
+                    path.color.to_hex_string(),
+                    layer_clip,
+                    layer_transform
+                )?;
+                if self.group_attributes {
+                    write!(f, " {}", style)?;
+                }
+                if let Some(blend) = &blend {
+                    write!(f, " {}", blend)?;
+                }
+                writeln!(f, ">")?;
+            }
+            let inherited = shared_style || (self.layer_names && self.group_attributes);
+            let style = match (inherited, blend.as_ref().filter(|_| !self.layer_names)) {
+                (true, None) => None,
+                (true, Some(blend)) => Some(blend.clone()),
+                (false, None) => Some(style.clone()),
+                (false, Some(blend)) => Some(format!("{} {}", style, blend)),
+            };
+            let style = style.as_deref();
+            let symbol = data
+                .get(i)
+                .and_then(|(string, offset)| Some((symbols.get(string.as_str())?, offset)));
+            match symbol {
+                Some((id, offset)) => {
+                    let id = self.id(&format!("shape-{}", id));
+                    write!(f, r##"<use xlink:href="#{}""##, id)?;
+                    if let Some(style) = style {
+                        write!(f, " {}", style)?;
+                    }
+                    writeln!(f, r#" x="{}" y="{}"/>"#, offset.x, offset.y)?;
+                }
+                None => path.fmt_with_precision(
+                    f,
+                    self.path_precision,
+                    &shape,
+                    style,
+                    margin as f64,
+                    self.scale,
+                )?,
+            }
+            if self.layer_names {
+                writeln!(f, "</g>")?;
+            }
+        }
+        if shared_style {
+            writeln!(f, "</g>")?;
+        }
+        if root_clip {
+            writeln!(f, "</g>")?;
+        }
+        if root_transform.is_some() {
+            writeln!(f, "</g>")?;
+        }
+        if self.opacity < 1.0 {
+            writeln!(f, "</g>")?;
         }
 
         writeln!(f, "</svg>")
@@ -52,22 +612,355 @@ impl fmt::Display for SvgFile {
 
 impl fmt::Display for SvgPath {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        self.fmt_with_precision(f, None)
+        let style = format!(r#"fill="{}""#, self.color.to_hex_string());
+        let shape = PathShape {
+            close_paths: true,
+            corner_rounding: 0.0,
+        };
+        self.fmt_with_precision(f, None, &shape, Some(&style), 0.0, 1.0)
     }
 }
 
 impl SvgPath {
-    fn fmt_with_precision(&self, f: &mut fmt::Formatter, precision: Option<u32>) -> fmt::Result {
-        let (string, offset) = self
-            .path
-            .to_svg_string(true, PointF64::default(), precision);
-        writeln!(
-            f,
-            "<path d=\"{}\" fill=\"{}\" transform=\"translate({},{})\"/>",
-            string,
-            self.color.to_hex_string(),
-            offset.x,
-            offset.y
-        )
+    fn fmt_with_precision(
+        &self,
+        f: &mut fmt::Formatter,
+        precision: Option<u32>,
+        shape: &PathShape,
+        style: Option<&str>,
+        margin: f64,
+        scale: f64,
+    ) -> fmt::Result {
+        let (string, offset) = self.to_svg_data(precision, shape, margin, scale);
+        write!(f, "<path d=\"{}\"", string)?;
+        if let Some(style) = style {
+            write!(f, " {}", style)?;
+        }
+        writeln!(f, " transform=\"translate({},{})\"/>", offset.x, offset.y)
+    }
+
+    /// Path data relative to the first point, and the position of that point
+    fn to_svg_data(
+        &self,
+        precision: Option<u32>,
+        shape: &PathShape,
+        margin: f64,
+        scale: f64,
+    ) -> (String, PointF64) {
+        let scaled;
+        let path = if scale == 1.0 {
+            &self.path
+        } else {
+            scaled = scale_path(&self.path, scale);
+            &scaled
+        };
+        let (string, offset) = if shape.close_paths && shape.corner_rounding <= 0.0 {
+            path.to_svg_string(true, PointF64::default(), precision)
+        } else {
+            shaped_svg_string(path, precision, shape)
+        };
+        (string, offset + PointF64::new(margin, margin) * scale)
+    }
+}
+
+/// `path` with each closed subpath started at its topmost, then leftmost point, so that one
+/// outline traced from different points writes the same path data
+fn normalize_start(path: &CompoundPath) -> CompoundPath {
+    let paths = path
+        .paths
+        .iter()
+        .map(|element| match element {
+            CompoundPathElement::PathI32(p) => {
+                let path = rotate_closed(&p.path, 1, |p| (p.y as f64, p.x as f64));
+                CompoundPathElement::PathI32(PathI32::from_points(path))
+            }
+            CompoundPathElement::PathF64(p) => {
+                let path = rotate_closed(&p.path, 1, |p| (p.y, p.x));
+                CompoundPathElement::PathF64(PathF64::from_points(path))
+            }
+            CompoundPathElement::Spline(spline) => {
+                let mut spline = spline.clone();
+                // Curves take 3 points each past the first, so only every third is on the curve
+                spline.points = rotate_closed(&spline.points, 3, |p| (p.y, p.x));
+                CompoundPathElement::Spline(spline)
+            }
+        })
+        .collect();
+    CompoundPath { paths }
+}
+
+/// `points`, whose last point repeats the first, started over at the point with the
+/// smallest `key` among every `step`th one. Other point lists are returned as they are.
+fn rotate_closed<T: Copy + PartialEq>(
+    points: &[T],
+    step: usize,
+    key: impl Fn(&T) -> (f64, f64),
+) -> Vec<T> {
+    let n = points.len().saturating_sub(1);
+    if n == 0 || points[0] != points[n] || !n.is_multiple_of(step) {
+        return points.to_vec();
+    }
+    let start = (0..n)
+        .step_by(step)
+        .min_by(|&a, &b| key(&points[a]).partial_cmp(&key(&points[b])).unwrap())
+        .unwrap_or(0);
+    points[start..n]
+        .iter()
+        .chain(&points[..=start])
+        .copied()
+        .collect()
+}
+
+/// How subpaths are ended and cornered in the path data
+struct PathShape {
+    close_paths: bool,
+    corner_rounding: f64,
+}
+
+/// As `CompoundPath::to_svg_string`, with `shape` applied. Unless `shape.close_paths` is set,
+/// only the enclosed subpaths, those whose first and last points are at most a pixel apart
+/// on either axis, end with `Z`.
+fn shaped_svg_string(
+    path: &CompoundPath,
+    precision: Option<u32>,
+    shape: &PathShape,
+) -> (String, PointF64) {
+    let Some((first, _)) = path.paths.first().map(end_points) else {
+        return (String::new(), PointF64::default());
+    };
+    let origin = PointF64::default() - first;
+    let string = path
+        .paths
+        .iter()
+        .map(|element| {
+            let (start, end) = end_points(element);
+            let is_enclosed = shape.close_paths
+                || (start.x - end.x).abs() <= 1.0 && (start.y - end.y).abs() <= 1.0;
+            // Closing a subpath drops its last point, which must then repeat the first
+            let close = is_enclosed && (shape.close_paths || start == end);
+            let polygon = match element {
+                _ if shape.corner_rounding <= 0.0 => None,
+                CompoundPathElement::PathI32(p) => {
+                    Some(p.path.iter().map(|p| p.to_point_f64()).collect())
+                }
+                CompoundPathElement::PathF64(p) => Some(p.path.clone()),
+                CompoundPathElement::Spline(_) => None,
+            };
+            if let Some(mut points) = polygon {
+                if close {
+                    points.pop();
+                }
+                let points: Vec<_> = points.into_iter().map(|point| point + origin).collect();
+                return rounded_polygon_string(
+                    &points,
+                    is_enclosed,
+                    shape.corner_rounding,
+                    precision,
+                );
+            }
+            let string = match element {
+                CompoundPathElement::PathI32(p) => {
+                    p.to_svg_string(close, &origin.to_point_i32(), precision)
+                }
+                CompoundPathElement::PathF64(p) => p.to_svg_string(close, &origin, precision),
+                CompoundPathElement::Spline(s) => s.to_svg_string(close, &origin, precision),
+            };
+            if is_enclosed && !close {
+                string + "Z "
+            } else {
+                string
+            }
+        })
+        .collect();
+    (string, first)
+}
+
+/// Path data of a polygon with every corner replaced by a circular arc of `radius`, or of
+/// the largest radius that fits in half of the shorter of the two edges at the corner.
+/// The ends of open polygons keep their corners.
+fn rounded_polygon_string(
+    points: &[PointF64],
+    closed: bool,
+    radius: f64,
+    precision: Option<u32>,
+) -> String {
+    let n = points.len();
+    if n < 3 {
+        let mut string = String::new();
+        for (i, point) in points.iter().enumerate() {
+            let command = if i == 0 { 'M' } else { 'L' };
+            string += &format!("{}{} ", command, point.to_svg_string(precision));
+        }
+        if closed {
+            string += "Z ";
+        }
+        return string;
+    }
+    let mut string = String::new();
+    for i in 0..n {
+        let vertex = points[i];
+        let is_end = !closed && (i == 0 || i == n - 1);
+        let corner = if is_end {
+            None
+        } else {
+            fillet(points[(i + n - 1) % n], vertex, points[(i + 1) % n], radius)
+        };
+        let command = if i == 0 { 'M' } else { 'L' };
+        match corner {
+            Some((start, end, radius, sweep)) => {
+                let radius = NumberFormat::number_format(radius, precision);
+                string += &format!(
+                    "{}{} A{} {} 0 0 {} {} ",
+                    command,
+                    start.to_svg_string(precision),
+                    radius,
+                    radius,
+                    sweep as u8,
+                    end.to_svg_string(precision)
+                );
+            }
+            None => string += &format!("{}{} ", command, vertex.to_svg_string(precision)),
+        }
+    }
+    if closed {
+        string += "Z ";
+    }
+    string
+}
+
+/// The start and end of the arc rounding the corner at `vertex` between the edges from
+/// `previous` and to `next`, its radius, and whether it turns clockwise on screen.
+/// Straight and degenerate corners have none.
+fn fillet(
+    previous: PointF64,
+    vertex: PointF64,
+    next: PointF64,
+    radius: f64,
+) -> Option<(PointF64, PointF64, f64, bool)> {
+    let (to_previous, to_next) = (previous - vertex, next - vertex);
+    let (length_previous, length_next) = (to_previous.norm(), to_next.norm());
+    if length_previous == 0.0 || length_next == 0.0 {
+        return None;
+    }
+    let (u, v) = (to_previous / length_previous, to_next / length_next);
+    let angle = u.dot(v).clamp(-1.0, 1.0).acos();
+    if angle < 1e-6 || std::f64::consts::PI - angle < 1e-6 {
+        return None;
+    }
+    let half_tan = (angle / 2.0).tan();
+    // Distance from the vertex to the points where the arc touches the edges
+    let distance = (radius / half_tan).min(length_previous.min(length_next) / 2.0);
+    let cross = (vertex.x - previous.x) * (next.y - vertex.y)
+        - (vertex.y - previous.y) * (next.x - vertex.x);
+    Some((
+        vertex + u * distance,
+        vertex + v * distance,
+        distance * half_tan,
+        cross > 0.0,
+    ))
+}
+
+/// The first and last points of a subpath
+pub(crate) fn end_points(element: &CompoundPathElement) -> (PointF64, PointF64) {
+    match element {
+        CompoundPathElement::PathI32(p) => (
+            p.path[0].to_point_f64(),
+            p.path[p.path.len() - 1].to_point_f64(),
+        ),
+        CompoundPathElement::PathF64(p) => (p.path[0], p.path[p.path.len() - 1]),
+        CompoundPathElement::Spline(s) => (s.points[0], s.points[s.points.len() - 1]),
+    }
+}
+
+/// Multiply every coordinate of `path` by `scale`
+fn scale_path(path: &CompoundPath, scale: f64) -> CompoundPath {
+    let mut scaled = CompoundPath::new();
+    for element in &path.paths {
+        match element {
+            CompoundPathElement::PathI32(p) => scaled.add_path_f64(PathF64::from_points(
+                p.path.iter().map(|p| p.to_point_f64() * scale).collect(),
+            )),
+            CompoundPathElement::PathF64(p) => scaled.add_path_f64(PathF64::from_points(
+                p.path.iter().map(|&p| p * scale).collect(),
+            )),
+            CompoundPathElement::Spline(s) => {
+                let mut s = s.clone();
+                for point in s.points.iter_mut() {
+                    *point *= scale;
+                }
+                scaled.add_spline(s);
+            }
+        }
+    }
+    scaled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square_svg(viewbox_margin: u32) -> SvgFile {
+        let mut svg = SvgFile::new(10, 20, None);
+        svg.viewbox_margin = viewbox_margin;
+        let mut path = CompoundPath::new();
+        path.add_path_f64(PathF64::from_points(vec![
+            PointF64::new(2.0, 3.0),
+            PointF64::new(6.0, 3.0),
+            PointF64::new(6.0, 7.0),
+            PointF64::new(2.0, 7.0),
+            PointF64::new(2.0, 3.0),
+        ]));
+        svg.add_path(path, Color::new(255, 0, 0));
+        svg
+    }
+
+    #[test]
+    fn viewbox_margin_pads_the_viewbox() {
+        let string = square_svg(5).to_string();
+        assert!(string.contains(r#"width="20" height="30" viewBox="0 0 20 30""#));
+        // Paths move by the margin
+        assert!(
+            string.contains(r#"transform="translate(7,8)""#),
+            "{}",
+            string
+        );
+    }
+
+    #[test]
+    fn no_viewbox_without_margin() {
+        let string = square_svg(0).to_string();
+        assert!(string.contains(r#"width="10" height="20">"#));
+        assert!(!string.contains("viewBox"));
+        assert!(
+            string.contains(r#"transform="translate(2,3)""#),
+            "{}",
+            string
+        );
+    }
+
+    #[test]
+    fn dedupe_matches_outlines_from_any_start() {
+        let mut svg = SvgFile::new(20, 20, None);
+        svg.dedupe_paths = true;
+        let square = |x: f64, y: f64, start: usize| {
+            let mut corners = vec![
+                PointF64::new(x, y),
+                PointF64::new(x + 4.0, y),
+                PointF64::new(x + 4.0, y + 4.0),
+                PointF64::new(x, y + 4.0),
+            ];
+            corners.rotate_left(start);
+            corners.push(corners[0]);
+            let mut path = CompoundPath::new();
+            path.add_path_f64(PathF64::from_points(corners));
+            path
+        };
+        svg.add_path(square(1.0, 1.0, 0), Color::new(255, 0, 0));
+        svg.add_path(square(10.0, 10.0, 2), Color::new(0, 0, 255));
+        let string = svg.to_string();
+        assert!(string.contains(r#"xmlns:xlink="http://www.w3.org/1999/xlink""#));
+        assert_eq!(string.matches("<symbol").count(), 1, "{}", string);
+        assert!(string.contains(r##"<use xlink:href="#vt-shape-1""##));
+        assert!(string.contains(r#"x="10" y="10"/>"#), "{}", string);
     }
 }
@@ -0,0 +1,101 @@
+use image::codecs::png::PngEncoder;
+use image::{ColorType, ImageError};
+use tiny_skia::{ColorU8, Paint, PathBuilder, Pixmap, PixmapPaint, Stroke, Transform};
+
+use super::svg::SvgFile;
+use super::vector::{PathSegment, VectorPath};
+
+/// Width of the outlines, in pixels
+const LINE_WIDTH: f32 = 1.0;
+
+/// PNG rendering of the outlines of an `SvgFile` in red, over the original image if it was
+/// embedded, to check a trace against its input. One pixel per pixel of the image, without
+/// the viewbox margin or scale.
+pub struct TracePng<'a> {
+    svg: &'a SvgFile,
+}
+
+impl<'a> TracePng<'a> {
+    pub fn new(svg: &'a SvgFile) -> Self {
+        Self { svg }
+    }
+
+    /// The RGBA PNG
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        let (width, height) = (self.svg.width as u32, self.svg.height as u32);
+        let mut pixmap = Pixmap::new(width, height)
+            .ok_or_else(|| String::from("Cannot render an empty image."))?;
+
+        if let Some(original) = &self.svg.original {
+            let image = image::load_from_memory(&original.png)
+                .map_err(|_| String::from("Cannot decode the original image."))?
+                .to_rgba8();
+            let mut below = Pixmap::new(image.width(), image.height())
+                .ok_or_else(|| String::from("Cannot render an empty image."))?;
+            for (pixel, source) in below.pixels_mut().iter_mut().zip(image.pixels()) {
+                let [r, g, b, a] = source.0;
+                *pixel = ColorU8::from_rgba(r, g, b, a).premultiply();
+            }
+            // Same centering as the <image> of the SVG
+            let x = (self.svg.width as i32 - image.width() as i32) / 2;
+            let y = (self.svg.height as i32 - image.height() as i32) / 2;
+            pixmap.draw_pixmap(
+                x,
+                y,
+                below.as_ref(),
+                &PixmapPaint::default(),
+                Transform::identity(),
+                None,
+            );
+        }
+
+        let mut paint = Paint::default();
+        paint.set_color_rgba8(255, 0, 0, 255);
+        let stroke = Stroke {
+            width: LINE_WIDTH,
+            ..Stroke::default()
+        };
+        for path in VectorPath::from_svg(self.svg) {
+            let mut builder = PathBuilder::new();
+            for segment in path.segments {
+                match segment {
+                    PathSegment::MoveTo(p) => builder.move_to(p.x as f32, p.y as f32),
+                    PathSegment::LineTo(p) => builder.line_to(p.x as f32, p.y as f32),
+                    PathSegment::CubicBezierTo {
+                        control1,
+                        control2,
+                        end,
+                    } => builder.cubic_to(
+                        control1.x as f32,
+                        control1.y as f32,
+                        control2.x as f32,
+                        control2.y as f32,
+                        end.x as f32,
+                        end.y as f32,
+                    ),
+                    PathSegment::ClosePath => builder.close(),
+                }
+            }
+            if let Some(outline) = builder.finish() {
+                pixmap.stroke_path(&outline, &paint, &stroke, Transform::identity(), None);
+            }
+        }
+
+        encode_png(&pixmap).map_err(|_| String::from("Cannot encode the trace as PNG."))
+    }
+}
+
+/// Encode `pixmap` as an RGBA PNG, undoing the premultiplied alpha of tiny-skia
+pub(crate) fn encode_png(pixmap: &Pixmap) -> Result<Vec<u8>, ImageError> {
+    let pixels: Vec<u8> = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+    let mut png = vec![];
+    PngEncoder::new(&mut png).encode(&pixels, pixmap.width(), pixmap.height(), ColorType::Rgba8)?;
+    Ok(png)
+}
@@ -0,0 +1,156 @@
+use std::fmt;
+
+use super::config::{is_valid_id_prefix, ColorMode, Config, PathSimplifyMode, StrokeMode};
+
+/// How much a `ValidationWarning` matters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// A setting has no effect with the others
+    Hint,
+    /// The settings work together, but likely give poor results
+    Warning,
+    /// The conversion will fail with these settings
+    Error,
+}
+
+/// An issue found by `Config::validate`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationWarning {
+    pub field: &'static str,
+    pub message: String,
+    pub severity: Severity,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Hint => "hint",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        })
+    }
+}
+
+impl fmt::Display for ValidationWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+impl Config {
+    /// Check the settings against each other, without converting anything.
+    /// Unlike `ConfigBuilder::build`, every issue is reported and none stops the conversion,
+    /// though those of `Severity::Error` make it fail. Ranges of single fields are not checked.
+    pub fn validate(&self) -> Vec<ValidationWarning> {
+        use Severity::*;
+        let mut warnings = vec![];
+        let mut report = |severity, field, message| {
+            warnings.push(ValidationWarning {
+                field,
+                message,
+                severity,
+            })
+        };
+        let binary = matches!(self.color_mode, ColorMode::Binary);
+
+        if matches!(self.stroke_mode, StrokeMode::Stroked) && !binary {
+            report(
+                Error,
+                "stroke_mode",
+                String::from("stroked paths are only supported in binary color mode"),
+            );
+        }
+        if self.canny_preprocess && !binary {
+            report(
+                Error,
+                "canny_preprocess",
+                String::from("Canny edge detection is only supported in binary color mode"),
+            );
+        }
+        if !is_valid_id_prefix(&self.output_id_prefix) {
+            report(
+                Error,
+                "output_id_prefix",
+                String::from("may only hold letters, digits, '-' and '_'"),
+            );
+        }
+        if self.canny_preprocess && self.canny_low_threshold > self.canny_high_threshold {
+            report(
+                Error,
+                "canny_low_threshold",
+                format!(
+                    "{} is above canny_high_threshold {}",
+                    self.canny_low_threshold, self.canny_high_threshold
+                ),
+            );
+        }
+
+        if binary && self.filter_speckle > 8 {
+            report(
+                Warning,
+                "filter_speckle",
+                format!(
+                    "{} drops every patch under {} pixels, which erases the fine lines of line art",
+                    self.filter_speckle,
+                    self.filter_speckle * self.filter_speckle
+                ),
+            );
+        }
+        if matches!(self.mode, PathSimplifyMode::Spline) && self.max_iterations <= 1 {
+            report(
+                Warning,
+                "max_iterations",
+                format!(
+                    "{} leaves curves unsmoothed in spline mode",
+                    self.max_iterations
+                ),
+            );
+        }
+
+        if binary {
+            if self.max_colors.is_some() {
+                report(
+                    Hint,
+                    "max_colors",
+                    String::from("ignored in binary color mode"),
+                );
+            }
+        } else if self.dither {
+            report(
+                Hint,
+                "dither",
+                String::from("only applies in binary color mode"),
+            );
+        }
+        if self.binary_threshold_otsu && self.binary_threshold != Config::default().binary_threshold
+        {
+            report(
+                Hint,
+                "binary_threshold",
+                String::from("ignored, as binary_threshold_otsu picks the threshold"),
+            );
+        }
+        if self.stroke_width.is_some() && matches!(self.stroke_mode, StrokeMode::Filled) {
+            report(
+                Hint,
+                "stroke_width",
+                String::from("ignored, as stroke_mode is filled"),
+            );
+        }
+        if self.corner_rounding > 0.0 && !matches!(self.mode, PathSimplifyMode::Polygon) {
+            report(
+                Hint,
+                "corner_rounding",
+                String::from("only applies in polygon mode"),
+            );
+        }
+        if self.cpu_threads.is_some() && !self.parallel {
+            report(
+                Hint,
+                "cpu_threads",
+                String::from("ignored, as parallel is off"),
+            );
+        }
+        warnings
+    }
+}
@@ -0,0 +1,117 @@
+use visioncortex::{CompoundPathElement, PointF64};
+
+use super::config::Rgba;
+use super::svg::{end_points, SvgFile, SvgPath};
+
+/// A drawing command of a `VectorPath`, in pixels of the converted image
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathSegment {
+    /// Start a subpath
+    MoveTo(PointF64),
+    LineTo(PointF64),
+    /// Cubic Bézier curve from the current point to `end`
+    CubicBezierTo {
+        control1: PointF64,
+        control2: PointF64,
+        end: PointF64,
+    },
+    /// Draw a line back to the start of the subpath and end it
+    ClosePath,
+}
+
+/// A traced path with its paint, before it is written in an output format
+#[derive(Debug, Clone, PartialEq)]
+pub struct VectorPath {
+    /// Set for filled paths
+    pub fill: Option<Rgba>,
+    /// Set for the outlines of `StrokeMode::Stroked`
+    pub stroke: Option<Rgba>,
+    pub segments: Vec<PathSegment>,
+}
+
+impl VectorPath {
+    /// The paths of `svg` in stacking order, subpaths closed as in the SVG output.
+    /// The margin, scale and translation of the SVG are not applied, and the corners of
+    /// polygons are not rounded.
+    pub fn from_svg(svg: &SvgFile) -> Vec<Self> {
+        svg.paths
+            .iter()
+            .map(|path| Self::from_svg_path(path, svg.stroke_width.is_some(), svg.close_paths))
+            .collect()
+    }
+
+    fn from_svg_path(path: &SvgPath, stroked: bool, close_paths: bool) -> Self {
+        let color = Rgba {
+            r: path.color.r,
+            g: path.color.g,
+            b: path.color.b,
+            a: path.color.a,
+        };
+        let mut segments = vec![];
+        for element in &path.path.paths {
+            let points: Vec<PointF64> = match element {
+                CompoundPathElement::PathI32(p) => {
+                    p.path.iter().map(|p| p.to_point_f64()).collect()
+                }
+                CompoundPathElement::PathF64(p) => p.path.clone(),
+                CompoundPathElement::Spline(s) => s.points.clone(),
+            };
+            let Some(&start) = points.first() else {
+                continue;
+            };
+            let (_, end) = end_points(element);
+            // Same rule as the SVG output
+            let is_enclosed =
+                close_paths || (start.x - end.x).abs() <= 1.0 && (start.y - end.y).abs() <= 1.0;
+            segments.push(PathSegment::MoveTo(start));
+            match element {
+                CompoundPathElement::Spline(_) => {
+                    for curve in points[1..].chunks_exact(3) {
+                        segments.push(PathSegment::CubicBezierTo {
+                            control1: curve[0],
+                            control2: curve[1],
+                            end: curve[2],
+                        });
+                    }
+                }
+                _ => {
+                    // Closed polygons repeat the first point at the end
+                    let repeated = is_enclosed && (close_paths || start == end);
+                    let last = if repeated {
+                        points.len() - 1
+                    } else {
+                        points.len()
+                    };
+                    for &point in &points[1..last.max(1)] {
+                        segments.push(PathSegment::LineTo(point));
+                    }
+                }
+            }
+            if is_enclosed {
+                segments.push(PathSegment::ClosePath);
+            }
+        }
+        Self {
+            fill: (!stroked).then_some(color),
+            stroke: stroked.then_some(color),
+            segments,
+        }
+    }
+}
+
+/// `#RRGGBB` of `color`, without alpha as the SVG writes path colors
+pub(crate) fn hex_string(color: Rgba) -> String {
+    format!("#{:02X}{:02X}{:02X}", color.r, color.g, color.b)
+}
+
+/// `value` rounded to 3 decimal places
+pub(crate) fn round_number(value: f64) -> f64 {
+    let rounded = (value * 1000.0).round() / 1000.0;
+    // Avoid printing negative zero
+    rounded + 0.0
+}
+
+/// `value` rounded to 3 decimal places, without trailing zeros
+pub(crate) fn number(value: f64) -> String {
+    round_number(value).to_string()
+}
@@ -0,0 +1,36 @@
+use crate::*;
+use wasm_bindgen::prelude::*;
+
+/// WebAssembly binding.
+/// `rgba_bytes` holds `width * height` pixels of 4 bytes each, as in a canvas `ImageData`,
+/// and `config_json` is a `Config` serialized as JSON.
+#[wasm_bindgen]
+pub fn convert_raw_pixels_to_svg(
+    width: u32,
+    height: u32,
+    rgba_bytes: &[u8],
+    config_json: &str,
+) -> Result<String, JsValue> {
+    let (width, height) = (width as usize, height as usize);
+    let Some(len) = width
+        .checked_mul(height)
+        .and_then(|area| area.checked_mul(4))
+    else {
+        return Err(JsValue::from_str("width and height are too large"));
+    };
+    if rgba_bytes.len() != len {
+        return Err(JsValue::from_str(
+            "rgba_bytes does not match the given width and height",
+        ));
+    }
+    let config =
+        Config::from_json_str(config_json).map_err(|err| JsValue::from_str(&err.to_string()))?;
+
+    let img = ColorImage {
+        pixels: rgba_bytes.to_vec(),
+        width,
+        height,
+    };
+    let svg = convert(img, config).map_err(|msg| JsValue::from_str(&msg))?;
+    Ok(svg.to_string())
+}
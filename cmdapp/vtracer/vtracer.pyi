@@ -2,9 +2,9 @@ from typing import Optional
 
 def convert_image_to_svg_py(image_path: str, 
                             out_path: str,   
-                            colormode: Optional[str] = None,        # ["color"] or "binary"
+                            colormode: Optional[str] = None,        # ["color"], "binary" or "grayscale"
                             hierarchical: Optional[str] = None,     # ["stacked"] or "cutout"
-                            mode: Optional[str] = None,             # ["spline"], "polygon", "none"
+                            mode: Optional[str] = None,             # ["spline"], "polygon", "none", "bezier"
                             filter_speckle: Optional[int] = None,   # default: 4
                             color_precision: Optional[int] = None,  # default: 6
                             layer_difference: Optional[int] = None, # default: 16
@@ -0,0 +1,54 @@
+// Copyright 2023 Tsang Hao Fung. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The types of the Rust source files written by `vtracer --format rust`, which bake the
+//! traced paths into a binary as `pub const PATHS: &[Path]`. Without dependencies and
+//! `no_std`, for embedded targets.
+
+#![no_std]
+
+/// An sRGB color with alpha
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+}
+
+/// A drawing command of a `Path`, in pixels of the traced image
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Segment {
+    /// Start a subpath at (x, y)
+    MoveTo(f32, f32),
+    LineTo(f32, f32),
+    /// Cubic Bézier curve from the current point through the control points
+    /// (x1, y1) and (x2, y2) to (x, y)
+    CubicTo(f32, f32, f32, f32, f32, f32),
+    /// Draw a line back to the start of the subpath and end it
+    Close,
+}
+
+/// A traced path with its paint. Paths are listed in stacking order and filled with the
+/// nonzero winding rule.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Path {
+    /// Set for filled paths
+    pub fill: Option<Color>,
+    /// Set for outlines, drawn `STROKE_WIDTH` wide
+    pub stroke: Option<Color>,
+    pub segments: &'static [Segment],
+}
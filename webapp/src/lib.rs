@@ -12,7 +12,7 @@
 )]
 use wasm_bindgen::prelude::*;
 
-mod conversion;
+pub mod conversion;
 mod canvas;
 mod common;
 mod svg;